@@ -1,371 +1,455 @@
-use proc_macro2::{Ident, Span, TokenStream};
-use quote::{format_ident, quote};
+use std::ops::Range;
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    parse_quote, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Error, Fields,
-    Generics, Index, Result,
+    parse_quote, spanned::Spanned, Data, DeriveInput, Error, Expr, Field, Fields, Index, Member,
+    Path, Result, Type, WhereClause, WherePredicate,
 };
 
-use crate::util::{parse_top_attrs, strip_raw, with_cast, with_ty, ParsedAttributes};
+use crate::{
+    util::{
+        add_param, alias_generics, enum_path, field_bounds, fn_wrapper_defs, parse_top_attrs,
+        strip_raw, validate_attrs, validate_transparent, via_value_defs, with_cast, with_ty,
+        BoundKind, Bounds, DataAttributes, Errors, ParsedAttributes, TopAttributes, Transparent,
+    },
+    version::Version,
+};
 
 pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
     let _ = input.generics.make_where_clause();
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
     let where_clause = where_clause.unwrap();
 
-    let mut impl_input_params = Punctuated::default();
-    impl_input_params.push(parse_quote! { __S: Fallible + ?Sized });
-
-    for param in input.generics.params.iter() {
-        impl_input_params.push(param.clone());
-    }
-
-    let serialize_impl_input_generics = Generics {
-        lt_token: Some(Default::default()),
-        params: impl_input_params,
-        gt_token: Some(Default::default()),
-        where_clause: input.generics.where_clause.clone(),
-    };
-
+    let serialize_impl_input_generics =
+        add_param(&input.generics, parse_quote! { __S: Fallible + ?Sized });
     let (serialize_impl_generics, _, _) = serialize_impl_input_generics.split_for_impl();
-
-    let from_tys = parse_top_attrs(&input.attrs)?;
-
-    if from_tys.is_empty() {
-        let msg = "requires top level attribute `#[archive_with(from(TypeName))]`";
-
-        return Err(Error::new(Span::call_site(), msg));
-    }
+    let alias_generics = alias_generics(&input.generics);
+
+    let mut errors = Errors::default();
+    let top_attrs = parse_top_attrs(&input.attrs, &mut errors);
+    let version = top_attrs.version;
+
+    let data_attrs = validate_attrs(&input.data, version, &mut errors);
+    validate(&input, &top_attrs, &data_attrs, &mut errors);
+    errors.finish()?;
+
+    // Without errors, the attributes of all fields and variants are parsed
+    let field_attrs = &data_attrs.fields;
+
+    let TopAttributes {
+        from: from_tys,
+        crate_path,
+        other_error,
+        partial,
+        exhaustive,
+        ref omitted,
+        ref bound,
+        ref into,
+        ref transparent,
+        ..
+    } = top_attrs;
 
     let name = &input.ident;
     let generics = &input.generics;
 
-    let (archive_impl, serialize_impl): (TokenStream, TokenStream) = match input.data {
-        Data::Struct(ref data) => {
-            match data.fields {
-                Fields::Named(ref fields) => {
-                    let mut archive_where = where_clause.clone();
-                    let mut serialize_where = where_clause.clone();
-
-                    for field in fields.named.iter() {
-                        let (ty, _) = with_ty(field)?;
+    let mut archive_items = TokenStream::new();
+    let mut serialize_items = TokenStream::new();
 
-                        archive_where.predicates.push(parse_quote! { #ty: Archive });
-
-                        serialize_where
-                            .predicates
-                            .push(parse_quote! { #ty: Serialize<__S> });
-                    }
+    let (archive_impl, serialize_impl): (TokenStream, TokenStream) = if let Some(into) = into {
+        proxy(&input, &from_tys, into, bound, version)
+    } else {
+        match input.data {
+            Data::Struct(ref data) => {
+                match data.fields {
+                    Fields::Named(ref fields) => {
+                        let mut archive_where = where_clause.clone();
+                        let mut serialize_where = where_clause.clone();
+
+                        let archive_bounds =
+                            field_bounds(BoundKind::Archive, bound, field_attrs, version)?;
+                        let serialize_bounds =
+                            field_bounds(BoundKind::Serialize, bound, field_attrs, version)?;
+                        archive_where.predicates.extend(archive_bounds);
+                        serialize_where.predicates.extend(serialize_bounds);
+
+                        let values = GetterValues::new(
+                            field_attrs.iter().zip(data.fields.members()),
+                            transparent.as_ref(),
+                            false,
+                            version,
+                        );
+                        archive_items.extend(values.archive_items(&input));
+                        serialize_items.extend(values.serialize_items());
+                        values.extend_where(&mut serialize_where);
+
+                        let archive_impls = from_tys
+                            .iter()
+                            .map(|from_ty| {
+                                let resolve_fields =
+                                    fields.named.iter().enumerate().map(|(i, field)| {
+                                        let name = &field.ident;
+                                        let member = Member::Named(name.clone().unwrap());
+                                        let attrs = &field_attrs[i].1;
+                                        let ty = attrs.from.as_ref().unwrap_or(&field.ty);
+                                        let remote = attrs.remote_member(member.clone());
+
+                                        let field_var = values.resolve_var(
+                                            i,
+                                            ty,
+                                            remote_value(
+                                                attrs,
+                                                &remote,
+                                                from_ty,
+                                                ty,
+                                                transparent.as_ref(),
+                                            ),
+                                        );
+                                        let field =
+                                            values.cast(i, field, attrs, parse_quote!(__field))?;
+                                        let resolve = version.resolve_field(
+                                            &member,
+                                            &field,
+                                            &parse_quote!(resolver.#name),
+                                        );
 
-                    let archive_impls = from_tys
-                        .iter()
-                        .map(|from_ty| {
-                            let resolve_fields = fields.named.iter().map(|field| {
-                                let name = &field.ident;
-                                let attrs = ParsedAttributes::new(&field.attrs).unwrap();
-                                let ty = attrs.from.as_ref().unwrap_or(&field.ty);
-
-                                let expr = attrs.getter.as_ref().map_or_else(
-                                    || parse_quote! { (field.#name) },
-                                    |getter| getter.make_expr(from_ty),
-                                );
-                                let field = with_cast(field, parse_quote!(__field)).unwrap();
+                                        Ok(quote! {
+                                            #field_var
+                                            #resolve
+                                        })
+                                    });
+                                let resolve_fields = resolve_fields.collect::<Result<Vec<_>>>()?;
 
-                                quote! {
-                                    let (fp, fo) = out_field!(out.#name);
-                                    let __field: &#ty = &#expr;
-                                    ::rkyv::Archive::resolve(#field, pos + fp, resolver.#name, fo);
-                                }
-                            });
-
-                            quote! {
-                                impl #impl_generics ArchiveWith<#from_ty>
-                                for #name #ty_generics #archive_where {
-                                    type Archived = <Self as Archive>::Archived;
-                                    type Resolver = <Self as Archive>::Resolver;
-
-                                    #[allow(clippy::unit_arg)]
-                                    #[inline]
-                                    unsafe fn resolve_with(
-                                        field: &#from_ty,
-                                        pos: usize,
-                                        resolver: Self::Resolver,
-                                        out: *mut Self::Archived,
-                                    ) {
+                                let destructure = values.destructure();
+                                let resolve_with = version.resolve_with(
+                                    from_ty,
+                                    quote! {
+                                        #destructure
                                         #( #resolve_fields )*
-                                    }
-                                }
-                            }
-                        })
-                        .collect();
-
-                    let serialize_impls = from_tys
-                        .iter()
-                        .map(|from_ty| {
-                            let field_vars = fields.named.iter().map(|field| {
-                                let name = &field.ident;
-                                let ident = format_ident!("__{}", name.as_ref().unwrap());
-                                let attrs = ParsedAttributes::new(&field.attrs).unwrap();
-                                let ty = attrs.from.as_ref().unwrap_or(&field.ty);
-
-                                let expr = attrs.getter.as_ref().map_or_else(
-                                    || parse_quote! { (field.#name) },
-                                    |getter| getter.make_expr(from_ty),
+                                    },
                                 );
+                                let resolver = values.resolver_ty(&input);
 
-                                quote! { let #ident: &#ty = &#expr; }
-                            });
-
-                            let resolver_values = fields.named.iter().map(|field| {
-                                let name = &field.ident;
-                                let ident = format_ident!("__{}", name.as_ref().unwrap());
-                                let expr = parse_quote!( #ident );
-                                let field = with_cast(field, expr).unwrap();
-
-                                quote! { #name: Serialize::<__S>::serialize(#field, serializer)? }
-                            });
-
-                            quote! {
-                                impl #serialize_impl_generics SerializeWith<#from_ty, __S>
-                                for #name #ty_generics #serialize_where {
-                                    #[inline]
-                                    fn serialize_with(
-                                        field: &#from_ty,
-                                        serializer: &mut __S,
-                                    ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
-                                        #( #field_vars )*
-                                        Ok(Self::Resolver {
-                                            #( #resolver_values, )*
-                                        })
+                                Ok(quote! {
+                                    impl #impl_generics ArchiveWith<#from_ty>
+                                    for #name #ty_generics #archive_where {
+                                        type Archived = <Self as Archive>::Archived;
+                                        type Resolver = #resolver;
+
+                                        #resolve_with
                                     }
-                                }
-                            }
-                        })
-                        .collect();
+                                })
+                            })
+                            .collect::<Result<_>>()?;
+
+                        let serialize_impls = from_tys
+                            .iter()
+                            .map(|from_ty| {
+                                let field_vars =
+                                    fields.named.iter().enumerate().map(|(i, field)| {
+                                        let name = &field.ident;
+                                        let ident = format_ident!("__{}", name.as_ref().unwrap());
+                                        let attrs = &field_attrs[i].1;
+                                        let ty = attrs.from.as_ref().unwrap_or(&field.ty);
+                                        let remote = attrs
+                                            .remote_member(Member::Named(name.clone().unwrap()));
+
+                                        let expr = remote_value(
+                                            attrs,
+                                            &remote,
+                                            from_ty,
+                                            ty,
+                                            transparent.as_ref(),
+                                        );
 
-                    (archive_impls, serialize_impls)
-                }
-                Fields::Unnamed(ref fields) => {
-                    let mut archive_where = where_clause.clone();
-                    let mut serialize_where = where_clause.clone();
+                                        Ok(values.field_var(i, &ident, ty, expr))
+                                    });
+                                let field_vars = field_vars.collect::<Result<Vec<_>>>()?;
 
-                    for field in fields.unnamed.iter() {
-                        let (ty, _) = with_ty(field)?;
+                                let resolver_values =
+                                    fields.named.iter().enumerate().map(|(i, field)| {
+                                        let name = &field.ident;
+                                        let ident = format_ident!("__{}", name.as_ref().unwrap());
+                                        let expr = parse_quote!( #ident );
+                                        let field = values.cast(i, field, &field_attrs[i].1, expr)?;
 
-                        archive_where
-                            .predicates
-                            .push(parse_quote! { #ty: ::rkyv::Archive });
+                                        Ok(quote! {
+                                            #name: Serialize::<__S>::serialize(#field, serializer)?
+                                        })
+                                    });
+                                let resolver_values =
+                                    resolver_values.collect::<Result<Vec<_>>>()?;
+                                let construct_resolver = values.construct_resolver(
+                                    quote! { __SelfResolver { #( #resolver_values, )* } },
+                                    0..fields.named.len(),
+                                );
 
-                        serialize_where
-                            .predicates
-                            .push(parse_quote! { #ty: Serialize<__S> });
-                    }
+                                Ok(quote! {
+                                    impl #serialize_impl_generics SerializeWith<#from_ty, __S>
+                                    for #name #ty_generics #serialize_where {
+                                        #[inline]
+                                        fn serialize_with(
+                                            field: &#from_ty,
+                                            serializer: &mut __S,
+                                        ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
+                                            type __SelfResolver #alias_generics = <#name #ty_generics as Archive>::Resolver;
+                                            #( #field_vars )*
+                                            Ok(#construct_resolver)
+                                        }
+                                    }
+                                })
+                            })
+                            .collect::<Result<_>>()?;
 
-                    let archive_impls = from_tys
-                        .iter()
-                        .map(|from_ty| {
-                            let resolve_fields =
-                                fields.unnamed.iter().enumerate().map(|(i, field)| {
-                                    let index = Index::from(i);
-                                    let attrs = ParsedAttributes::new(&field.attrs).unwrap();
-                                    let ty = attrs.from.as_ref().unwrap_or(&field.ty);
+                        (archive_impls, serialize_impls)
+                    }
+                    Fields::Unnamed(ref fields) => {
+                        let mut archive_where = where_clause.clone();
+                        let mut serialize_where = where_clause.clone();
+
+                        let archive_bounds =
+                            field_bounds(BoundKind::Archive, bound, field_attrs, version)?;
+                        let serialize_bounds =
+                            field_bounds(BoundKind::Serialize, bound, field_attrs, version)?;
+                        archive_where.predicates.extend(archive_bounds);
+                        serialize_where.predicates.extend(serialize_bounds);
+
+                        let values = GetterValues::new(
+                            field_attrs.iter().zip(data.fields.members()),
+                            transparent.as_ref(),
+                            false,
+                            version,
+                        );
+                        archive_items.extend(values.archive_items(&input));
+                        serialize_items.extend(values.serialize_items());
+                        values.extend_where(&mut serialize_where);
+
+                        let archive_impls = from_tys
+                            .iter()
+                            .map(|from_ty| {
+                                let resolve_fields =
+                                    fields.unnamed.iter().enumerate().map(|(i, field)| {
+                                        let index = Index::from(i);
+                                        let attrs = &field_attrs[i].1;
+                                        let ty = attrs.from.as_ref().unwrap_or(&field.ty);
+                                        let remote =
+                                            attrs.remote_member(Member::Unnamed(index.clone()));
+
+                                        let field_var = values.resolve_var(
+                                            i,
+                                            ty,
+                                            remote_value(
+                                                attrs,
+                                                &remote,
+                                                from_ty,
+                                                ty,
+                                                transparent.as_ref(),
+                                            ),
+                                        );
+                                        let field =
+                                            values.cast(i, field, attrs, parse_quote!(__field))?;
+                                        let resolve = version.resolve_field(
+                                            &Member::Unnamed(index.clone()),
+                                            &field,
+                                            &parse_quote!(resolver.#index),
+                                        );
 
-                                    let expr = attrs.getter.as_ref().map_or_else(
-                                        || parse_quote! { (field.#index) },
-                                        |getter| getter.make_expr(from_ty),
-                                    );
-                                    let field = with_cast(field, parse_quote!(__field)).unwrap();
+                                        Ok(quote! {
+                                            #field_var
+                                            #resolve
+                                        })
+                                    });
+                                let resolve_fields = resolve_fields.collect::<Result<Vec<_>>>()?;
 
+                                let destructure = values.destructure();
+                                let resolve_with = version.resolve_with(
+                                    from_ty,
                                     quote! {
-                                        let (fp, fo) = out_field!(out.#index);
-                                        let __field: &#ty = &#expr;
-                                        ::rkyv::Archive::resolve(
-                                            #field,
-                                            pos + fp,
-                                            resolver.#index,
-                                            fo
-                                        );
-                                    }
-                                });
-
-                            quote! {
-                                impl #impl_generics ArchiveWith<#from_ty>
-                                for #name #ty_generics #archive_where {
-                                    type Archived = <Self as Archive>::Archived;
-                                    type Resolver = <Self as Archive>::Resolver;
-
-                                    #[allow(clippy::unit_arg)]
-                                    #[inline]
-                                    unsafe fn resolve_with(
-                                        field: &#from_ty,
-                                        pos: usize,
-                                        resolver: Self::Resolver,
-                                        out: *mut Self::Archived,
-                                    ) {
+                                        #destructure
                                         #( #resolve_fields )*
-                                    }
-                                }
-                            }
-                        })
-                        .collect();
-
-                    let serialize_impls = from_tys
-                        .iter()
-                        .map(|from_ty| {
-                            let field_vars = fields.unnamed.iter().enumerate().map(|(i, field)| {
-                                let index = Index::from(i);
-                                let ident = format_ident!("__{i}", span = index.span());
-                                let attrs = ParsedAttributes::new(&field.attrs).unwrap();
-                                let ty = attrs.from.as_ref().unwrap_or(&field.ty);
-
-                                let expr = attrs.getter.as_ref().map_or_else(
-                                    || parse_quote! { (field.#index) },
-                                    |getter| getter.make_expr(from_ty),
+                                    },
                                 );
+                                let resolver = values.resolver_ty(&input);
 
-                                quote! { let #ident: &#ty = &#expr; }
-                            });
+                                Ok(quote! {
+                                    impl #impl_generics ArchiveWith<#from_ty>
+                                    for #name #ty_generics #archive_where {
+                                        type Archived = <Self as Archive>::Archived;
+                                        type Resolver = #resolver;
 
-                            let resolver_values =
-                                fields.unnamed.iter().enumerate().map(|(i, field)| {
-                                    let index = Index::from(i);
-                                    let ident = format_ident!("__{i}", span = index.span());
-                                    let expr = parse_quote!( #ident );
-                                    let field = with_cast(field, expr).unwrap();
+                                        #resolve_with
+                                    }
+                                })
+                            })
+                            .collect::<Result<_>>()?;
+
+                        let serialize_impls = from_tys
+                            .iter()
+                            .map(|from_ty| {
+                                let field_vars =
+                                    fields.unnamed.iter().enumerate().map(|(i, field)| {
+                                        let index = Index::from(i);
+                                        let ident = format_ident!("__{i}", span = index.span());
+                                        let attrs = &field_attrs[i].1;
+                                        let ty = attrs.from.as_ref().unwrap_or(&field.ty);
+                                        let remote = attrs.remote_member(Member::Unnamed(index));
+
+                                        let expr = remote_value(
+                                            attrs,
+                                            &remote,
+                                            from_ty,
+                                            ty,
+                                            transparent.as_ref(),
+                                        );
 
-                                    quote! { Serialize::<__S>::serialize(#field, serializer)? }
-                                });
+                                        Ok(values.field_var(i, &ident, ty, expr))
+                                    });
+                                let field_vars = field_vars.collect::<Result<Vec<_>>>()?;
 
-                            // FIXME: rust currently requires the actual name instead of
-                            //        something like `<Self as Archive>::Resolver(...)`
-                            let resolver_name = Ident::new(&format!("{name}Resolver"), name.span());
-
-                            quote! {
-                                impl #serialize_impl_generics SerializeWith<#from_ty, __S>
-                                for #name #ty_generics #serialize_where {
-                                    #[inline]
-                                    fn serialize_with(
-                                        field: &#from_ty,
-                                        serializer: &mut __S,
-                                    ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
-                                        #( #field_vars )*
-                                        Ok(#resolver_name(
-                                            #( #resolver_values, )*
-                                        ))
-                                    }
-                                }
-                            }
-                        })
-                        .collect();
+                                let resolver_values =
+                                    fields.unnamed.iter().enumerate().map(|(i, field)| {
+                                        let index = Index::from(i);
+                                        let ident = format_ident!("__{i}", span = index.span());
+                                        let expr = parse_quote!( #ident );
+                                        let field = values.cast(i, field, &field_attrs[i].1, expr)?;
 
-                    (archive_impls, serialize_impls)
-                }
-                Fields::Unit => {
-                    let archive_impls = from_tys
-                        .iter()
-                        .map(|from_ty| {
-                            quote! {
-                                impl #impl_generics ::rkyv::with::ArchiveWith<#from_ty>
-                                for #name #ty_generics #where_clause {
-                                    type Archived = <Self as ::rkyv::Archive>::Archived;
-                                    type Resolver = <Self as ::rkyv::Archive>::Resolver;
-
-                                    #[allow(clippy::unit_arg)]
-                                    #[inline]
-                                    unsafe fn resolve_with(
-                                        field: &#from_ty,
-                                        pos: usize,
-                                        resolver: Self::Resolver,
-                                        out: *mut Self::Archived,
-                                    ) {
-                                    }
-                                }
-                            }
-                        })
-                        .collect();
+                                        Ok(quote! {
+                                            #index: Serialize::<__S>::serialize(#field, serializer)?
+                                        })
+                                    });
+                                let resolver_values =
+                                    resolver_values.collect::<Result<Vec<_>>>()?;
+                                // Braces so that the resolver does not have to be
+                                // named, e.g. in case of `#[archive(resolver = "...")]`
+                                let construct_resolver = values.construct_resolver(
+                                    quote! { __SelfResolver { #( #resolver_values, )* } },
+                                    0..fields.unnamed.len(),
+                                );
 
-                    let serialize_impls = from_tys
-                        .iter()
-                        .map(|from_ty| {
-                            let resolver_name = Ident::new(&format!("{name}Resolver"), name.span());
-
-                            quote! {
-                                impl #serialize_impl_generics
-                                ::rkyv::with::SerializeWith<#from_ty, __S>
-                                for #name #ty_generics #where_clause {
-                                    #[inline]
-                                    fn serialize_with(
-                                        field: &#from_ty,
-                                        serializer: &mut __S,
-                                    ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
-                                        Ok(#resolver_name)
+                                Ok(quote! {
+                                    impl #serialize_impl_generics SerializeWith<#from_ty, __S>
+                                    for #name #ty_generics #serialize_where {
+                                        #[inline]
+                                        fn serialize_with(
+                                            field: &#from_ty,
+                                            serializer: &mut __S,
+                                        ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
+                                            type __SelfResolver #alias_generics = <#name #ty_generics as Archive>::Resolver;
+                                            #( #field_vars )*
+                                            Ok(#construct_resolver)
+                                        }
                                     }
-                                }
-                            }
-                        })
-                        .collect();
-
-                    (archive_impls, serialize_impls)
-                }
-            }
-        }
-        Data::Enum(ref data) => {
-            let mut archive_where = where_clause.clone();
-            let mut serialize_where = where_clause.clone();
+                                })
+                            })
+                            .collect::<Result<_>>()?;
 
-            for variant in data.variants.iter() {
-                match variant.fields {
-                    Fields::Named(ref fields) => {
-                        for field in fields.named.iter() {
-                            let (ty, _) = with_ty(field)?;
+                        (archive_impls, serialize_impls)
+                    }
+                    Fields::Unit => {
+                        let archive_impls = from_tys
+                            .iter()
+                            .map(|from_ty| {
+                                let resolve_with = version.resolve_with(from_ty, quote! {});
 
-                            archive_where
-                                .predicates
-                                .push(parse_quote!( #ty: ::rkyv::Archive ));
+                                quote! {
+                                    impl #impl_generics __rkyv::with::ArchiveWith<#from_ty>
+                                    for #name #ty_generics #where_clause {
+                                        type Archived = <Self as __rkyv::Archive>::Archived;
+                                        type Resolver = <Self as __rkyv::Archive>::Resolver;
 
-                            serialize_where
-                                .predicates
-                                .push(parse_quote!( #ty: Serialize<__S> ));
-                        }
-                    }
-                    Fields::Unnamed(ref fields) => {
-                        for field in fields.unnamed.iter() {
-                            let (ty, _) = with_ty(field)?;
+                                        #resolve_with
+                                    }
+                                }
+                            })
+                            .collect();
 
-                            archive_where
-                                .predicates
-                                .push(parse_quote!( #ty: ::rkyv::Archive ));
+                        let serialize_impls = from_tys
+                            .iter()
+                            .map(|from_ty| {
+                                quote! {
+                                    impl #serialize_impl_generics
+                                    __rkyv::with::SerializeWith<#from_ty, __S>
+                                    for #name #ty_generics #where_clause {
+                                        #[inline]
+                                        fn serialize_with(
+                                            field: &#from_ty,
+                                            serializer: &mut __S,
+                                        ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
+                                            Ok(Self::Resolver {})
+                                        }
+                                    }
+                                }
+                            })
+                            .collect();
 
-                            serialize_where
-                                .predicates
-                                .push(parse_quote!( #ty: Serialize<__S> ));
-                        }
+                        (archive_impls, serialize_impls)
                     }
-                    Fields::Unit => {}
                 }
             }
-
-            let archive_impls = from_tys
+            Data::Enum(ref data) => {
+                let mut archive_where = where_clause.clone();
+                let mut serialize_where = where_clause.clone();
+
+                let other = data_attrs
+                    .variants
+                    .iter()
+                    .find_map(|(variant, attrs)| attrs.other.then_some(&variant.ident));
+
+                let archive_bounds = field_bounds(BoundKind::Archive, bound, field_attrs, version)?;
+                let serialize_bounds =
+                    field_bounds(BoundKind::Serialize, bound, field_attrs, version)?;
+                archive_where.predicates.extend(archive_bounds);
+                serialize_where.predicates.extend(serialize_bounds);
+
+                let values = GetterValues::new(
+                    field_attrs
+                        .iter()
+                        .zip(data.variants.iter().flat_map(|v| v.fields.members())),
+                    None,
+                    true,
+                    version,
+                );
+                archive_items.extend(values.archive_items(&input));
+                serialize_items.extend(values.serialize_items());
+                values.extend_where(&mut serialize_where);
+
+                // Index of each variant's first field among the fields of all variants
+                let offsets: Vec<usize> = data
+                    .variants
+                    .iter()
+                    .scan(0, |offset, v| {
+                        let start = *offset;
+                        *offset += v.fields.len();
+
+                        Some(start)
+                    })
+                    .collect();
+
+                let archive_impls = from_tys
                 .iter()
                 .map(|from_ty| {
+                    let enum_path = enum_path(from_ty);
+
                     let archived_variant_tags = data.variants.iter().map(|v| {
                         let variant = &v.ident;
 
                         quote! { #variant }
                     });
 
-                    let archived_variant_structs = data.variants.iter().map(|v| {
+                    let archived_variant_structs = data.variants.iter().zip(&offsets).map(|(v, &offset)| {
                         let variant = &v.ident;
                         let archived_variant_name =
                             Ident::new(&format!("ArchivedVariant{}", strip_raw(variant)), v.span());
 
-                        match v.fields {
+                        let archived_variant_struct = match v.fields {
                             Fields::Named(ref fields) => {
-                                let fields = fields.named.iter().map(|field| {
+                                let fields = fields.named.iter().enumerate().map(|(i, field)| {
                                     let name = &field.ident;
-                                    let (ty, _) = with_ty(field).unwrap();
+                                    let ty = with_ty(field, &field_attrs[offset + i].1, version)?;
 
-                                    quote! { #name: Archived<#ty> }
+                                    Ok(quote! { #name: Archived<#ty> })
                                 });
+                                let fields = fields.collect::<Result<Vec<_>>>()?;
 
                                 quote! {
                                     #[repr(C)]
@@ -377,11 +461,12 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                                 }
                             }
                             Fields::Unnamed(ref fields) => {
-                                let fields = fields.unnamed.iter().map(|field| {
-                                    let (ty, _) = with_ty(field).unwrap();
+                                let fields = fields.unnamed.iter().enumerate().map(|(i, field)| {
+                                    let ty = with_ty(field, &field_attrs[offset + i].1, version)?;
 
-                                    quote! { Archived<#ty> }
+                                    Ok(quote! { Archived<#ty> })
                                 });
+                                let fields = fields.collect::<Result<Vec<_>>>()?;
 
                                 quote! {
                                     #[repr(C)]
@@ -393,24 +478,38 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                                 }
                             }
                             Fields::Unit => quote! {},
-                        }
+                        };
+
+                        Ok(archived_variant_struct)
                     });
+                    let archived_variant_structs =
+                        archived_variant_structs.collect::<Result<Vec<_>>>()?;
 
-                    let resolve_arms = data.variants.iter().map(|v| {
+                    let resolve_arms = data_attrs.variants.iter().zip(&offsets).map(|(&(v, ref variant_attrs), &offset)| {
                         let variant = &v.ident;
                         let archived_variant_name =
                             Ident::new(&format!("ArchivedVariant{}", strip_raw(variant)), v.span());
+                        let remote_variants = variant_attrs.remote_variants(variant);
 
-                        match v.fields {
+                        let arm = match v.fields {
                             Fields::Named(ref fields) => {
-                                let self_bindings = fields.named.iter().map(|f| {
+                                let self_bindings = fields.named.iter().enumerate().map(|(i, f)| {
                                     let name = &f.ident;
                                     let binding = Ident::new(
                                         &format!("self_{}", strip_raw(name.as_ref().unwrap())),
                                         name.span(),
                                     );
+                                    let remote = field_attrs[offset + i].1.remote_member(Member::Named(name.clone().unwrap()));
 
-                                    quote! { #name: #binding }
+                                    if values.is_converted(offset + i) {
+                                        return quote! { #remote: _ };
+                                    }
+
+                                    quote! { #remote: #binding }
+                                });
+                                let self_bindings: Vec<_> = self_bindings.collect();
+                                let patterns = remote_variants.iter().map(|remote| {
+                                    quote! { #enum_path::#remote { #(#self_bindings,)* } }
                                 });
 
                                 let resolver_bindings = fields.named.iter().map(|f| {
@@ -423,7 +522,7 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                                     quote! { #name: #binding }
                                 });
 
-                                let resolves = fields.named.iter().map(|f| {
+                                let resolves = fields.named.iter().enumerate().map(|(i, f)| {
                                     let name = &f.ident;
                                     let self_binding = Ident::new(
                                         &format!("self_{}", strip_raw(name.as_ref().unwrap())),
@@ -433,33 +532,45 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                                         &format!("resolver_{}", strip_raw(name.as_ref().unwrap())),
                                         name.span(),
                                     );
+                                    let attrs = &field_attrs[offset + i].1;
+                                    let ty = attrs.from.as_ref().unwrap_or(&f.ty);
+                                    let field_var = values.resolve_var(
+                                        offset + i,
+                                        ty,
+                                        parse_quote! { (*#self_binding) },
+                                    );
                                     let value =
-                                        with_cast(f, parse_quote! { #self_binding }).unwrap();
+                                        values.cast(offset + i, f, attrs, parse_quote!(__field))?;
+                                    let resolve = version.resolve_field(
+                                        &Member::Named(name.clone().unwrap()),
+                                        &value,
+                                        &parse_quote!(#resolver_binding),
+                                    );
 
-                                    quote! {
-                                        let (fp, fo) = out_field!(out.#name);
-                                        ::rkyv::Archive::resolve(
-                                            #value,
-                                            pos + fp,
-                                            #resolver_binding,
-                                            fo
-                                        );
-                                    }
+                                    Ok(quote! {
+                                        #field_var
+                                        #resolve
+                                    })
                                 });
+                                let resolves = resolves.collect::<Result<Vec<_>>>()?;
+
+                                let tag = version.resolve_variant_tag(
+                                    quote! { #archived_variant_name #ty_generics },
+                                    &parse_quote!(__tag),
+                                    variant,
+                                );
+
                                 quote! {
                                     __SelfResolver::#variant {
                                         #( #resolver_bindings, )*
                                     } => {
                                         match field {
-                                            #from_ty::#variant { #(#self_bindings,)* } => {
-                                                let out = out
-                                                    .cast::<#archived_variant_name #ty_generics>();
-                                                ::core::ptr::addr_of_mut!((*out).__tag)
-                                                    .write(ArchivedTag::#variant);
+                                            #( #patterns )|* => {
+                                                #tag
                                                 #( #resolves )*
                                             },
                                             #[allow(unreachable_patterns)]
-                                            _ => ::core::hint::unreachable_unchecked(),
+                                            _ => unsafe { ::core::hint::unreachable_unchecked() },
                                         }
                                     }
                                 }
@@ -468,9 +579,18 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                                 let self_bindings =
                                     fields.unnamed.iter().enumerate().map(|(i, f)| {
                                         let name = Ident::new(&format!("self_{}", i), f.span());
+                                        let remote = field_attrs[offset + i].1.remote_member(Member::Unnamed(Index::from(i)));
 
-                                        quote! { #name }
+                                        if values.is_converted(offset + i) {
+                                            return quote! { #remote: _ };
+                                        }
+
+                                        quote! { #remote: #name }
                                     });
+                                let self_bindings: Vec<_> = self_bindings.collect();
+                                let patterns = remote_variants.iter().map(|remote| {
+                                    quote! { #enum_path::#remote { #(#self_bindings,)* } }
+                                });
 
                                 let resolver_bindings =
                                     fields.unnamed.iter().enumerate().map(|(i, f)| {
@@ -484,47 +604,80 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                                     let self_binding = Ident::new(&format!("self_{}", i), f.span());
                                     let resolver_binding =
                                         Ident::new(&format!("resolver_{}", i), f.span());
+                                    let attrs = &field_attrs[offset + i].1;
+                                    let ty = attrs.from.as_ref().unwrap_or(&f.ty);
+                                    let field_var = values.resolve_var(
+                                        offset + i,
+                                        ty,
+                                        parse_quote! { (*#self_binding) },
+                                    );
                                     let value =
-                                        with_cast(f, parse_quote! { #self_binding }).unwrap();
+                                        values.cast(offset + i, f, attrs, parse_quote!(__field))?;
+                                    let resolve = version.resolve_field(
+                                        &Member::Unnamed(index),
+                                        &value,
+                                        &parse_quote!(#resolver_binding),
+                                    );
 
-                                    quote! {
-                                        let (fp, fo) = out_field!(out.#index);
-                                        ::rkyv::Archive::resolve(
-                                            #value,
-                                            pos + fp,
-                                            #resolver_binding,
-                                            fo
-                                        );
-                                    }
+                                    Ok(quote! {
+                                        #field_var
+                                        #resolve
+                                    })
                                 });
+                                let resolves = resolves.collect::<Result<Vec<_>>>()?;
+
+                                let tag = version.resolve_variant_tag(
+                                    quote! { #archived_variant_name #ty_generics },
+                                    &parse_quote!(0),
+                                    variant,
+                                );
 
                                 quote! {
                                     __SelfResolver::#variant(
                                         #( #resolver_bindings, )*
                                     ) => {
                                         match field {
-                                            #from_ty::#variant(#(#self_bindings,)*) => {
-                                                let out = out
-                                                    .cast::<#archived_variant_name #ty_generics>();
-                                                ::core::ptr::addr_of_mut!((*out).0)
-                                                    .write(ArchivedTag::#variant);
+                                            #( #patterns )|* => {
+                                                #tag
                                                 #( #resolves )*
                                             },
                                             #[allow(unreachable_patterns)]
-                                            _ => ::core::hint::unreachable_unchecked(),
+                                            _ => unsafe { ::core::hint::unreachable_unchecked() },
                                         }
                                     }
                                 }
                             }
-                            Fields::Unit => quote! {
-                                <Self as Archive>::Resolver::#variant => {
-                                    out.cast::<ArchivedTag>().write(ArchivedTag::#variant);
+                            Fields::Unit => {
+                                let tag = version.resolve_unit_variant(variant);
+
+                                quote! {
+                                    <Self as Archive>::Resolver::#variant => {
+                                        #tag
+                                    }
                                 }
-                            },
-                        }
+                            }
+                        };
+
+                        Ok(arm)
                     });
+                    let resolve_arms = resolve_arms.collect::<Result<Vec<_>>>()?;
+
+                    let destructure = values.destructure();
+                    let resolve_with = version.resolve_with(
+                        from_ty,
+                        quote! {
+                            type __SelfResolver #alias_generics = <#name #ty_generics as Archive>::Resolver;
+                            #destructure
 
-                    quote! {
+                            match resolver {
+                                #( #resolve_arms, )*
+                            }
+                        },
+                    );
+
+                    let resolver = values.resolver_ty(&input);
+
+                    Ok(quote! {
                         #[repr(u8)]
                         enum ArchivedTag {
                             #( #archived_variant_tags, )*
@@ -535,123 +688,244 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
                         impl #impl_generics ArchiveWith<#from_ty>
                         for #name #ty_generics #archive_where {
                             type Archived = <Self as Archive>::Archived;
-                            type Resolver = <Self as Archive>::Resolver;
+                            type Resolver = #resolver;
 
-                            #[allow(clippy::unit_arg)]
-                            #[inline]
-                            unsafe fn resolve_with(
-                                field: &#from_ty,
-                                pos: usize,
-                                resolver: <Self as Archive>::Resolver,
-                                out: *mut <Self as Archive>::Archived
-                            ) {
-                                type __SelfResolver #ty_generics = <#name #ty_generics as Archive>::Resolver;
-
-                                match resolver {
-                                    #( #resolve_arms, )*
-                                }
-                            }
+                            #resolve_with
                         }
-                    }
+                    })
                 })
-                .collect();
+                .collect::<Result<_>>()?;
+
+                // Catches variants that are unknown e.g. due to `#[non_exhaustive]`
+                // or that are not mapped on purpose due to `partial`
+                let other_arm =
+                    match (other, &other_error, &partial) {
+                        (Some(other), ..) => {
+                            let construct_resolver = values.construct_resolver(
+                                quote! { <Self as Archive>::Resolver::#other },
+                                0..0,
+                            );
+
+                            Some(quote! {
+                                #[allow(unreachable_patterns)]
+                                _ => #construct_resolver
+                            })
+                        }
+                        (None, Some(other_error), _) => {
+                            let (error, bounds) = version.serializer_error(other_error);
+                            serialize_where.predicates.extend(bounds);
+
+                            Some(quote! {
+                                #[allow(unreachable_patterns)]
+                                _ => return Err(#error)
+                            })
+                        }
+                        // Only `None` for rkyv 0.7 which was rejected during validation
+                        (None, None, Some(_)) => version.unmapped_variant_error(name).map(
+                            |(definition, error, bound)| {
+                                serialize_where.predicates.push(bound);
+                                serialize_items.extend(definition);
+
+                                quote! {
+                                    #[allow(unreachable_patterns)]
+                                    _ => return Err(#error)
+                                }
+                            },
+                        ),
+                        (None, None, None) => None,
+                    };
 
-            let serialize_impls = from_tys
+                let serialize_impls = from_tys
                 .iter()
                 .map(|from_ty| {
-                    let serialize_arms = data.variants.iter().map(|v| {
+                    let enum_path = enum_path(from_ty);
+
+                    let serialize_arms = data_attrs.variants.iter().zip(&offsets).map(|(&(v, ref variant_attrs), &offset)| {
                         let variant = &v.ident;
+                        let remote_variants = variant_attrs.remote_variants(variant);
 
-                        match v.fields {
+                        let arm = match v.fields {
                             Fields::Named(ref fields) => {
-                                let bindings = fields.named.iter().map(|field| {
+                                let bindings = fields.named.iter().enumerate().map(|(i, field)| {
                                     let name = &field.ident;
 
-                                    quote!(#name)
+                                    match field_attrs[offset + i].1.rename {
+                                        Some(ref remote) => quote!(#remote: #name),
+                                        None => quote!(#name),
+                                    }
+                                });
+                                let bindings: Vec<_> = bindings.collect();
+                                let patterns = remote_variants.iter().map(|remote| {
+                                    quote! { #enum_path::#remote { #( #bindings, )* } }
                                 });
 
-                                let fields = fields.named.iter().map(|field| {
+                                let field_vars = fields.named.iter().enumerate().map(|(i, field)| {
                                     let name = &field.ident;
-                                    let field = with_cast(field, parse_quote! { #name }).unwrap();
+                                    let ident = format_ident!("__{}", name.as_ref().unwrap());
+                                    let attrs = &field_attrs[offset + i].1;
+                                    let ty = attrs.from.as_ref().unwrap_or(&field.ty);
 
-                                    quote! {
+                                    values.field_var(offset + i, &ident, ty, parse_quote! { (*#name) })
+                                });
+                                let field_vars: Vec<_> = field_vars.collect();
+
+                                let resolver_values = fields.named.iter().enumerate().map(|(i, field)| {
+                                    let name = &field.ident;
+                                    let ident = format_ident!("__{}", name.as_ref().unwrap());
+                                    let field = values.cast(offset + i, field, &field_attrs[offset + i].1, parse_quote! { #ident })?;
+
+                                    Ok(quote! {
                                         #name: Serialize::<__S>::serialize(#field, serializer)?
-                                    }
+                                    })
                                 });
+                                let resolver_values = resolver_values.collect::<Result<Vec<_>>>()?;
+                                let construct_resolver = values.construct_resolver(
+                                    quote! { __SelfResolver::#variant { #( #resolver_values, )* } },
+                                    offset..offset + fields.named.len(),
+                                );
 
                                 quote! {
-                                    #from_ty::#variant { #( #bindings, )* } =>
-                                    __SelfResolver::#variant {
-                                        #( #fields, )*
+                                    #( #patterns )|* => {
+                                        #( #field_vars )*
+                                        #construct_resolver
                                     }
                                 }
                             }
                             Fields::Unnamed(ref fields) => {
                                 let bindings = fields.unnamed.iter().enumerate().map(|(i, f)| {
                                     let name = Ident::new(&format!("_{}", i), f.span());
+                                    let remote = field_attrs[offset + i].1.remote_member(Member::Unnamed(Index::from(i)));
 
-                                    quote! { #name }
+                                    quote! { #remote: #name }
+                                });
+                                let bindings: Vec<_> = bindings.collect();
+                                let patterns = remote_variants.iter().map(|remote| {
+                                    quote! { #enum_path::#remote { #( #bindings, )* } }
                                 });
 
-                                let fields = fields.unnamed.iter().enumerate().map(|(i, f)| {
+                                let field_vars = fields.unnamed.iter().enumerate().map(|(i, f)| {
                                     let binding = Ident::new(&format!("_{}", i), f.span());
-                                    let field = with_cast(f, parse_quote! { #binding }).unwrap();
+                                    let ident = format_ident!("__{i}", span = f.span());
+                                    let attrs = &field_attrs[offset + i].1;
+                                    let ty = attrs.from.as_ref().unwrap_or(&f.ty);
 
-                                    quote! {
+                                    values.field_var(offset + i, &ident, ty, parse_quote! { (*#binding) })
+                                });
+                                let field_vars: Vec<_> = field_vars.collect();
+
+                                let resolver_values = fields.unnamed.iter().enumerate().map(|(i, f)| {
+                                    let ident = format_ident!("__{i}", span = f.span());
+                                    let field = values.cast(offset + i, f, &field_attrs[offset + i].1, parse_quote! { #ident })?;
+
+                                    Ok(quote! {
                                         Serialize::<__S>::serialize(#field, serializer)?
-                                    }
+                                    })
                                 });
+                                let resolver_values = resolver_values.collect::<Result<Vec<_>>>()?;
+                                let construct_resolver = values.construct_resolver(
+                                    quote! { __SelfResolver::#variant(#( #resolver_values, )*) },
+                                    offset..offset + fields.unnamed.len(),
+                                );
 
                                 quote! {
-                                    #from_ty::#variant( #(#bindings,)* ) =>
-                                    __SelfResolver::#variant(#(#fields,)*)
+                                    #( #patterns )|* => {
+                                        #( #field_vars )*
+                                        #construct_resolver
+                                    }
                                 }
                             }
+                            // Covered by the fallback arm
+                            Fields::Unit if remote_variants.is_empty() => return Ok(None),
                             Fields::Unit => {
-                                quote! { #from_ty::#variant => <Self as Archive>::Resolver::#variant }
+                                let construct_resolver = values.construct_resolver(
+                                    quote! { <Self as Archive>::Resolver::#variant },
+                                    0..0,
+                                );
+
+                                quote! {
+                                    #( #enum_path::#remote_variants )|* =>
+                                    #construct_resolver
+                                }
                             }
-                        }
+                        };
+
+                        Ok(Some(arm))
                     });
+                    let serialize_arms = serialize_arms.collect::<Result<Vec<_>>>()?;
+                    let serialize_arms = serialize_arms.into_iter().flatten();
 
-                    quote! {
+                    Ok(quote! {
                         impl #serialize_impl_generics SerializeWith<#from_ty, __S>
                         for #name #ty_generics #serialize_where {
                             #[inline]
                             fn serialize_with(
                                 field: &#from_ty,
                                 serializer: &mut __S
-                            ) -> ::core::result::Result<<#name #ty_generics as Archive>::Resolver, __S::Error> {
-                                type __SelfResolver #ty_generics = <#name #ty_generics as Archive>::Resolver;
+                            ) -> ::core::result::Result<Self::Resolver, __S::Error> {
+                                type __SelfResolver #alias_generics = <#name #ty_generics as Archive>::Resolver;
                                 Ok(match field {
                                     #( #serialize_arms, )*
+                                    #other_arm
                                 })
                             }
                         }
-                    }
+                    })
                 })
-                .collect();
+                .collect::<Result<_>>()?;
 
-            (archive_impls, serialize_impls)
+                (archive_impls, serialize_impls)
+            }
+            Data::Union(_) => unreachable!("unions are rejected during validation"),
         }
-        Data::Union(_) => {
-            let msg = "ArchiveWith cannot be derived for unions";
+    };
 
-            return Err(Error::new_spanned(input, msg));
-        }
+    let exhaustive_checks = if exhaustive {
+        from_tys
+            .iter()
+            .map(|from_ty| {
+                let fallback = other_error.is_some() || partial.is_some();
+
+                exhaustive_check(&input, &data_attrs, from_ty, omitted, fallback)
+            })
+            .collect()
+    } else {
+        TokenStream::new()
+    };
+
+    // Field attributes are ignored when converting through `into`. Only the
+    // archived types of the wrappers are named, see `with_ty`, so they are only
+    // defined alongside `ArchiveWith`.
+    let fn_wrappers = if into.is_none() {
+        fn_wrapper_defs(&input, &data_attrs, false, version)
+    } else {
+        TokenStream::new()
     };
 
+    let fallible = version.fallible();
+
     let tokens = quote! {
         #[automatically_derived]
         const _: () = {
+            use #crate_path as __rkyv;
             use ::core::marker::PhantomData;
-            use ::rkyv::{out_field, Archive, Archived};
+            use __rkyv::{Archive, Archived, with::ArchiveWith};
+
+            #archive_items
+
+            #fn_wrappers
 
             #archive_impl
+
+            #exhaustive_checks
         };
 
         #[automatically_derived]
         const _: () = {
-            use ::rkyv::{out_field, Archive, Fallible, Serialize, with::SerializeWith};
+            use #crate_path as __rkyv;
+            use #fallible;
+            use __rkyv::{Archive, Serialize, with::{ArchiveWith, SerializeWith}};
+
+            #serialize_items
 
             #serialize_impl
         };
@@ -659,3 +933,856 @@ pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
 
     Ok(tokens)
 }
+
+/// Checks the top level attributes against the shape of the type.
+fn validate(
+    input: &DeriveInput,
+    top_attrs: &TopAttributes,
+    data_attrs: &DataAttributes<'_>,
+    errors: &mut Errors,
+) {
+    let TopAttributes {
+        ref from,
+        ref other_error,
+        ref partial,
+        ref into,
+        ref from_mirror,
+        ref transparent,
+        version,
+        ..
+    } = *top_attrs;
+
+    if from.is_empty() {
+        let msg = "requires top level attribute `#[archive_with(from(TypeName))]`";
+        errors.push(Error::new_spanned(&input.ident, msg));
+    }
+
+    if let Some(transparent) = transparent {
+        validate_transparent(&input.data, transparent, errors);
+
+        if let Some(into) = into {
+            let msg = "`into` cannot be combined with `transparent`";
+            errors.push(Error::new_spanned(into, msg));
+        }
+
+        if top_attrs.exhaustive {
+            let msg = "`exhaustive` cannot be combined with `transparent`";
+            errors.push(Error::new_spanned(&transparent.key, msg));
+        }
+    }
+
+    if into.is_some() {
+        if let Some(other_error) = other_error {
+            let msg = "`other_error` cannot be combined with `into`";
+            errors.push(Error::new_spanned(other_error, msg));
+        }
+
+        if let Some(partial) = partial {
+            let msg = "`partial` cannot be combined with `into`";
+            errors.push(Error::new_spanned(partial, msg));
+        }
+
+        if top_attrs.exhaustive {
+            let msg = "`exhaustive` cannot be combined with `into`";
+            errors.push(Error::new_spanned(&input.ident, msg));
+        }
+    } else if let Some(from_mirror) = from_mirror {
+        let msg = "`ArchiveWith` requires `into = \"...\"` alongside `from_mirror`";
+        errors.push(Error::new_spanned(&from_mirror.path, msg));
+    }
+
+    match input.data {
+        Data::Struct(_) => {
+            for (_, attrs) in data_attrs.fields.iter() {
+                let Some(ref path) = attrs.path else {
+                    continue;
+                };
+
+                if top_attrs.exhaustive {
+                    let msg = "`path` cannot be combined with `exhaustive`";
+                    errors.push(Error::new_spanned(path, msg));
+                }
+
+                if transparent.is_some() {
+                    let msg = "`path` cannot be combined with `transparent`";
+                    errors.push(Error::new_spanned(path, msg));
+                }
+            }
+
+            if let Some(other_error) = other_error {
+                let msg = "`other_error` is only supported for enums";
+                errors.push(Error::new_spanned(other_error, msg));
+            }
+
+            if let Some(partial) = partial {
+                let msg = "`partial` is only supported for enums";
+                errors.push(Error::new_spanned(partial, msg));
+            }
+        }
+        Data::Enum(_) => {
+            let mut other = false;
+
+            for (variant, attrs) in data_attrs.variants.iter() {
+                if !attrs.other {
+                    continue;
+                }
+
+                if !matches!(variant.fields, Fields::Unit) {
+                    let msg = "`other` is only supported on unit variants";
+                    errors.push(Error::new_spanned(variant, msg));
+                }
+
+                if other {
+                    let msg = "`other` can only be specified on a single variant";
+                    errors.push(Error::new_spanned(variant, msg));
+                }
+
+                if let Some(other_error) = other_error {
+                    let msg = "`other` cannot be combined with `other_error`";
+                    errors.push(Error::new_spanned(other_error, msg));
+                }
+
+                if let Some(partial) = partial {
+                    let msg = "`other` cannot be combined with `partial`";
+                    errors.push(Error::new_spanned(partial, msg));
+                }
+
+                other = true;
+            }
+
+            if let (Some(partial), None) = (partial, other_error) {
+                if !version.is_v0_8() {
+                    let msg = "rkyv 0.7 requires `other_error = \"...\"` for `partial`";
+                    errors.push(Error::new_spanned(partial, msg));
+                }
+            }
+        }
+        Data::Union(_) => {
+            let msg = "ArchiveWith cannot be derived for unions";
+            errors.push(Error::new_spanned(input, msg));
+        }
+    }
+}
+
+/// Expression for the value of a field within the remote type `from_ty`.
+fn remote_value(
+    attrs: &ParsedAttributes,
+    remote: &Member,
+    from_ty: &Type,
+    ty: &Type,
+    transparent: Option<&Transparent>,
+) -> Expr {
+    match (&attrs.getter, transparent) {
+        (Some(getter), _) => getter.make_expr(from_ty),
+        (None, Some(transparent)) => transparent.make_expr(from_ty, ty),
+        (None, None) => match attrs.path {
+            Some(ref path) => parse_quote! { (field.#path) },
+            None => parse_quote! { (field.#remote) },
+        },
+    }
+}
+
+/// Values of fields which are computed during serialization, i.e. those of
+/// getters, of `transparent` conversions through `Into`, and of conversions
+/// through `serialize_fn`, `via_into`, or `via_try_from`.
+///
+/// The values are stored in a resolver that wraps the mirror's resolver so
+/// that resolving reuses them instead of computing them again. Without
+/// stored values, the mirror's resolver is used as is.
+///
+/// For enums, the fields of all variants are covered but only the values of
+/// the serialized variant are stored, so all values are optional.
+struct GetterValues {
+    /// How the value of each field is obtained from the remote type.
+    sources: Vec<FieldSource>,
+    /// Type that the value of each field is converted into, alongside the
+    /// conversion of the reference `__{member}` to the value.
+    conversions: Vec<Option<(Type, Expr)>>,
+    /// Index within the stored values for each field, if its value is stored.
+    indices: Vec<Option<Index>>,
+    /// Types of the stored values.
+    types: Vec<Type>,
+    /// Variables that the values are bound to within `serialize_with`.
+    vars: Vec<Ident>,
+    /// Field that each stored value belongs to.
+    owners: Vec<usize>,
+    serialize_bounds: Vec<WherePredicate>,
+    /// Whether any getter takes ownership of the remote type.
+    owned_self: bool,
+    /// Whether any value is converted through `via_into` or `via_try_from`.
+    via_value: bool,
+    /// Whether the fields belong to the variants of an enum.
+    variants: bool,
+    version: Version,
+}
+
+/// How the value of a field is obtained from the remote type.
+enum FieldSource {
+    /// The remote type's field itself.
+    Field,
+    /// A getter, alongside the error type of `fallible = "..."` and the
+    /// conversion of its error into the serializer's error if the getter is
+    /// fallible.
+    Getter(Option<(Option<Type>, Expr)>),
+    /// A getter that returns a reference which dereferences to the given type.
+    Borrowed(Type),
+    /// The remote type itself converted through `Into` for `transparent`.
+    Transparent,
+}
+
+impl GetterValues {
+    fn new<'a>(
+        fields: impl IntoIterator<Item = (&'a (&'a Field, ParsedAttributes), Member)>,
+        transparent: Option<&Transparent>,
+        variants: bool,
+        version: Version,
+    ) -> Self {
+        let mut values = Self {
+            sources: Vec::new(),
+            conversions: Vec::new(),
+            indices: Vec::new(),
+            types: Vec::new(),
+            vars: Vec::new(),
+            owners: Vec::new(),
+            serialize_bounds: Vec::new(),
+            owned_self: false,
+            via_value: false,
+            variants,
+            version,
+        };
+
+        for (i, (&(field, ref attrs), member)) in fields.into_iter().enumerate() {
+            let ty = attrs.from.clone().unwrap_or_else(|| field.ty.clone());
+            let ident = match member {
+                Member::Named(ident) => format_ident!("__{}", ident),
+                Member::Unnamed(index) => format_ident!("__{}", index.index),
+            };
+
+            let source = match attrs.getter {
+                Some(ref getter) => {
+                    values.owned_self |= getter.owned_self;
+
+                    match (&getter.borrow, &getter.fallible) {
+                        (Some(borrowed), _) => {
+                            FieldSource::Borrowed(borrowed.clone().unwrap_or_else(|| ty.clone()))
+                        }
+                        (None, Some(error_ty)) => {
+                            let (error, bounds) = version.error_into(
+                                &parse_quote!(__S),
+                                parse_quote!(err),
+                                error_ty.as_ref(),
+                            );
+                            values.serialize_bounds.extend(bounds);
+
+                            FieldSource::Getter(Some((error_ty.clone(), error)))
+                        }
+                        (None, None) => FieldSource::Getter(None),
+                    }
+                }
+                None => match transparent {
+                    Some(transparent) if !transparent.as_ref => FieldSource::Transparent,
+                    _ => FieldSource::Field,
+                },
+            };
+
+            let conversion = attrs.conversion(field, parse_quote!(#ident));
+            values.via_value |= conversion.is_some() && attrs.serialize_fn.is_none();
+
+            // Converted values are stored instead of the values that they are
+            // converted from. Getters that may return a reference store either
+            // their value or a pointer to it, see `serialize_items`, and
+            // borrowed values are always stored as a pointer.
+            let stored = match (&conversion, &source) {
+                (Some((converted, _)), _) if variants => Some((
+                    parse_quote! { ::core::option::Option<#converted> },
+                    Self::converted_var(i),
+                )),
+                (Some((converted, _)), _) => Some((converted.clone(), Self::converted_var(i))),
+                (None, FieldSource::Getter(Some(_)) | FieldSource::Transparent) => {
+                    Some((ty, Self::value_var(i)))
+                }
+                (None, FieldSource::Getter(None)) => Some((
+                    parse_quote! { ::core::result::Result<#ty, *const #ty> },
+                    Self::value_var(i),
+                )),
+                (None, FieldSource::Borrowed(borrowed)) => {
+                    Some((parse_quote! { *const #borrowed }, Self::value_var(i)))
+                }
+                (None, FieldSource::Field) => None,
+            };
+
+            values.sources.push(source);
+            values.conversions.push(conversion);
+
+            let Some((ty, var)) = stored else {
+                values.indices.push(None);
+
+                continue;
+            };
+
+            values.indices.push(Some(Index::from(values.types.len())));
+            values.types.push(ty);
+            values.vars.push(var);
+            values.owners.push(i);
+        }
+
+        values
+    }
+
+    fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// The variable within `serialize_with` that holds the value of the
+    /// `i`th field's getter.
+    ///
+    /// Named by index rather than by the field's name so that it can't
+    /// collide with the variables of other fields.
+    fn value_var(i: usize) -> Ident {
+        format_ident!("__archive_with_value_{i}")
+    }
+
+    /// The variable within `serialize_with` that holds the converted value of
+    /// the `i`th field, see [`value_var`](Self::value_var).
+    fn converted_var(i: usize) -> Ident {
+        format_ident!("__archive_with_converted_{i}")
+    }
+
+    /// Whether the value of the `i`th field is converted, in which case the
+    /// remote value is not needed while resolving.
+    fn is_converted(&self, i: usize) -> bool {
+        self.conversions[i].is_some()
+    }
+
+    /// Whether the field's value is borrowed as `str` or a slice, which are
+    /// archived through the wrappers `BorrowedStr` and `BorrowedSlice`.
+    fn borrowed_wrapper(&self, i: usize) -> Option<Ident> {
+        if self.conversions[i].is_some() {
+            return None;
+        }
+
+        match self.sources[i] {
+            FieldSource::Borrowed(Type::Slice(_)) => Some(format_ident!("BorrowedSlice")),
+            FieldSource::Borrowed(Type::Path(ref path))
+                if path.qself.is_none() && path.path.is_ident("str") =>
+            {
+                Some(format_ident!("BorrowedStr"))
+            }
+            _ => None,
+        }
+    }
+
+    /// Items that `ArchiveWith` requires, i.e. the resolver and the wrappers of
+    /// borrowed values.
+    fn archive_items(&self, input: &DeriveInput) -> TokenStream {
+        let mut items = TokenStream::new();
+
+        if !self.is_empty() {
+            let name = &input.ident;
+            let generics = &input.generics;
+            let (_, ty_generics, where_clause) = generics.split_for_impl();
+            let mut where_clause = where_clause.unwrap().clone();
+            where_clause
+                .predicates
+                .push(parse_quote! { #name #ty_generics: Archive });
+            let types = &self.types;
+
+            // Public because it is the resolver of the mirror's impls, it
+            // still can't be named outside of the `const` block
+            items.extend(quote! {
+                pub struct __ArchiveWithResolver #generics #where_clause {
+                    resolver: <#name #ty_generics as Archive>::Resolver,
+                    values: ( #( #types, )* ),
+                    __phantom: ::core::marker::PhantomData<fn() -> #name #ty_generics>,
+                }
+            });
+        }
+
+        items.extend(self.wrapper_items(false));
+
+        items
+    }
+
+    /// Items that `SerializeWith` requires.
+    ///
+    /// `GetterValue` distinguishes getters that return a value, which is
+    /// stored as `Ok`, from those that return a reference, which is stored as
+    /// a pointer in `Err`. `GetterSelf` copies the remote type for getters
+    /// that take ownership if it is `Copy` and clones it otherwise.
+    fn serialize_items(&self) -> TokenStream {
+        let mut items = TokenStream::new();
+
+        if self
+            .sources
+            .iter()
+            .any(|source| matches!(source, FieldSource::Getter(None)))
+        {
+            items.extend(quote! {
+                trait GetterValue<T> {
+                    fn value(&self) -> &T;
+
+                    fn into_stored(self) -> ::core::result::Result<T, *const T>;
+                }
+
+                impl<T> GetterValue<T> for T {
+                    fn value(&self) -> &T {
+                        self
+                    }
+
+                    fn into_stored(self) -> ::core::result::Result<T, *const T> {
+                        ::core::result::Result::Ok(self)
+                    }
+                }
+
+                impl<T> GetterValue<T> for &T {
+                    fn value(&self) -> &T {
+                        self
+                    }
+
+                    fn into_stored(self) -> ::core::result::Result<T, *const T> {
+                        ::core::result::Result::Err(self)
+                    }
+                }
+            });
+        }
+
+        if self
+            .sources
+            .iter()
+            .any(|source| matches!(source, FieldSource::Getter(Some(_))))
+        {
+            items.extend(self.version.getter_result_items());
+        }
+
+        if self.owned_self {
+            items.extend(quote! {
+                struct GetterSelf<'a, T>(&'a T);
+
+                trait CopySelf<T> {
+                    fn to_owned_self(&self) -> T;
+                }
+
+                impl<T: Copy> CopySelf<T> for GetterSelf<'_, T> {
+                    fn to_owned_self(&self) -> T {
+                        *self.0
+                    }
+                }
+
+                trait CloneSelf<T> {
+                    fn to_owned_self(&self) -> T;
+                }
+
+                impl<T: Clone> CloneSelf<T> for &GetterSelf<'_, T> {
+                    fn to_owned_self(&self) -> T {
+                        T::clone(self.0)
+                    }
+                }
+            });
+        }
+
+        if self.via_value {
+            items.extend(via_value_defs());
+        }
+
+        items.extend(self.wrapper_items(true));
+
+        items
+    }
+
+    /// Defines `BorrowedStr` and `BorrowedSlice` if any value is borrowed as
+    /// `str` or a slice respectively.
+    ///
+    /// Both `const` blocks need their own definitions since `SerializeWith`
+    /// requires `ArchiveWith`.
+    fn wrapper_items(&self, with_serialize: bool) -> TokenStream {
+        let mut items = TokenStream::new();
+
+        let wrappers: Vec<_> = (0..self.sources.len())
+            .filter_map(|i| self.borrowed_wrapper(i))
+            .collect();
+
+        if wrappers.iter().any(|wrapper| wrapper == "BorrowedStr") {
+            items.extend(self.version.str_wrapper(with_serialize));
+        }
+
+        if wrappers.iter().any(|wrapper| wrapper == "BorrowedSlice") {
+            items.extend(self.version.slice_wrapper(with_serialize));
+        }
+
+        items
+    }
+
+    /// The resolver of the `ArchiveWith` impl.
+    fn resolver_ty(&self, input: &DeriveInput) -> TokenStream {
+        if self.is_empty() {
+            return quote! { <Self as Archive>::Resolver };
+        }
+
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+
+        quote! { __ArchiveWithResolver #ty_generics }
+    }
+
+    /// Adds the bounds that serializing the values requires.
+    fn extend_where(&self, serialize_where: &mut WhereClause) {
+        for (i, source) in self.sources.iter().enumerate() {
+            if let (Some(wrapper), FieldSource::Borrowed(borrowed)) =
+                (self.borrowed_wrapper(i), source)
+            {
+                serialize_where.predicates.push(parse_quote! {
+                    #wrapper: __rkyv::with::SerializeWith<#borrowed, __S>
+                });
+            }
+        }
+
+        serialize_where
+            .predicates
+            .extend(self.serialize_bounds.iter().cloned());
+    }
+
+    /// Splits the resolver within `resolve_with` into the mirror's resolver
+    /// and the values.
+    fn destructure(&self) -> Option<TokenStream> {
+        (!self.is_empty()).then(|| {
+            quote! { let __ArchiveWithResolver { resolver, values, .. } = resolver; }
+        })
+    }
+
+    /// Binds a reference to the value of the `i`th field to `__field` within
+    /// `resolve_with`.
+    ///
+    /// Uses the stored value if there is one and `expr` otherwise.
+    fn resolve_var(&self, i: usize, ty: &Type, expr: Expr) -> TokenStream {
+        if let (Some((converted, _)), Some(index)) = (&self.conversions[i], &self.indices[i]) {
+            if !self.variants {
+                return quote! { let __field: &#converted = &values.#index; };
+            }
+
+            return quote! {
+                let __field: &#converted = match values.#index {
+                    ::core::option::Option::Some(ref value) => value,
+                    // The values of the resolver's variant are always stored
+                    ::core::option::Option::None => unsafe { ::core::hint::unreachable_unchecked() },
+                };
+            };
+        }
+
+        let Some(ref index) = self.indices[i] else {
+            return quote! { let __field: &#ty = &#expr; };
+        };
+
+        match self.sources[i] {
+            FieldSource::Getter(Some(_)) | FieldSource::Transparent => {
+                quote! { let __field: &#ty = &values.#index; }
+            }
+            // SAFETY: The resolver is the result of serializing `field` which
+            // the getter borrowed the value from so the value is still valid
+            FieldSource::Getter(None) => quote! {
+                let __field: &#ty = match values.#index {
+                    ::core::result::Result::Ok(ref value) => value,
+                    ::core::result::Result::Err(value) => unsafe { &*value },
+                };
+            },
+            // SAFETY: See above
+            FieldSource::Borrowed(ref borrowed) => quote! {
+                let __field: &#borrowed = unsafe { &*values.#index };
+            },
+            FieldSource::Field => unreachable!("the values of fields are not stored"),
+        }
+    }
+
+    /// Binds a reference to the value `expr` of the `i`th field to `ident`
+    /// within `serialize_with`, which must be `__{member}`.
+    ///
+    /// The values of getters and conversions are bound by value as well so
+    /// that they can be stored in the resolver or borrowed.
+    fn field_var(&self, i: usize, ident: &Ident, ty: &Type, expr: Expr) -> TokenStream {
+        let value = Self::value_var(i);
+
+        let mut tokens = match self.sources[i] {
+            FieldSource::Field => quote! { let #ident: &#ty = &#expr; },
+            FieldSource::Getter(Some((ref error_ty, ref error))) => {
+                let result = self.version.getter_result(expr, error_ty.as_ref());
+
+                quote! {
+                    let #value: #ty = #result.map_err(|err| #error)?;
+                    let #ident: &#ty = &#value;
+                }
+            }
+            FieldSource::Getter(None) => quote! {
+                let #value = #expr;
+                let #ident: &#ty = <_ as GetterValue<#ty>>::value(&#value);
+            },
+            // Only references can be stored, see `GetterValues::new`
+            FieldSource::Borrowed(ref borrowed) => quote! {
+                let #value: &#borrowed = #expr;
+                let #ident: &#borrowed = #value;
+            },
+            FieldSource::Transparent => quote! {
+                let #value: #ty = #expr;
+                let #ident: &#ty = &#value;
+            },
+        };
+
+        if let Some((ref converted, ref conversion)) = self.conversions[i] {
+            let value = Self::converted_var(i);
+
+            tokens.extend(quote! {
+                let #value: #converted = #conversion;
+                let #ident: &#converted = &#value;
+            });
+        }
+
+        tokens
+    }
+
+    /// Wraps the reference `expr` to the value of the `i`th field.
+    ///
+    /// Values borrowed as `str` or a slice are archived as `String` or `Vec`
+    /// respectively, all others through the field's wrappers.
+    fn cast(&self, i: usize, field: &Field, attrs: &ParsedAttributes, expr: Expr) -> Result<Expr> {
+        match (self.borrowed_wrapper(i), &self.sources[i]) {
+            (Some(wrapper), FieldSource::Borrowed(borrowed)) => Ok(parse_quote! {
+                __rkyv::with::With::<#borrowed, #wrapper>::cast(#expr)
+            }),
+            _ => with_cast(field, attrs, expr, self.version),
+        }
+    }
+
+    /// Creates the resolver within `serialize_with` out of the mirror's
+    /// resolver `resolver` and the values of the fields within `bound`, i.e.
+    /// those that are bound in the current scope.
+    fn construct_resolver(&self, resolver: TokenStream, bound: Range<usize>) -> TokenStream {
+        if self.is_empty() {
+            return resolver;
+        }
+
+        let values = self.vars.iter().zip(&self.owners).map(|(var, owner)| {
+            if !bound.contains(owner) {
+                quote! { ::core::option::Option::None }
+            } else if self.variants {
+                quote! { ::core::option::Option::Some(#var) }
+            } else {
+                match (&self.conversions[*owner], &self.sources[*owner]) {
+                    (None, FieldSource::Getter(None)) => quote! { GetterValue::into_stored(#var) },
+                    (None, FieldSource::Borrowed(borrowed)) => quote! { #var as *const #borrowed },
+                    _ => quote! { #var },
+                }
+            }
+        });
+
+        quote! {
+            Self::Resolver {
+                resolver: #resolver,
+                values: ( #( #values, )* ),
+                __phantom: ::core::marker::PhantomData,
+            }
+        }
+    }
+}
+
+/// Archives and serializes the remote types by converting them into the
+/// mirror through `into` and then delegating to the mirror's own `Archive`
+/// and `Serialize` implementations.
+///
+/// The resolver carries the converted mirror so that `into` is only called
+/// once.
+fn proxy(
+    input: &DeriveInput,
+    from_tys: &[Type],
+    into: &Path,
+    bound: &Bounds,
+    version: Version,
+) -> (TokenStream, TokenStream) {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let serialize_impl_input_generics =
+        add_param(&input.generics, parse_quote! { __S: Fallible + ?Sized });
+    let (serialize_impl_generics, _, _) = serialize_impl_input_generics.split_for_impl();
+
+    let mut archive_where = where_clause.unwrap().clone();
+    let mut serialize_where = archive_where.clone();
+
+    match bound.get(BoundKind::Archive) {
+        Some(bounds) => archive_where.predicates.extend(bounds.iter().cloned()),
+        None => archive_where
+            .predicates
+            .push(parse_quote! { #name #ty_generics: Archive }),
+    }
+
+    match bound.get(BoundKind::Serialize) {
+        Some(bounds) => serialize_where.predicates.extend(bounds.iter().cloned()),
+        None => serialize_where
+            .predicates
+            .push(parse_quote! { #name #ty_generics: Serialize<__S> }),
+    }
+
+    let resolve = version.resolve(&parse_quote!(&mirror), &parse_quote!(resolver));
+
+    let archive_impls = from_tys
+        .iter()
+        .map(|from_ty| {
+            let resolve_with = version.resolve_with(
+                from_ty,
+                quote! {
+                    let (mirror, resolver) = resolver;
+                    #resolve
+                },
+            );
+
+            quote! {
+                impl #impl_generics ArchiveWith<#from_ty>
+                for #name #ty_generics #archive_where {
+                    type Archived = <Self as Archive>::Archived;
+                    type Resolver = (Self, <Self as Archive>::Resolver);
+
+                    #resolve_with
+                }
+            }
+        })
+        .collect();
+
+    let serialize_impls = from_tys
+        .iter()
+        .map(|from_ty| {
+            quote! {
+                impl #serialize_impl_generics SerializeWith<#from_ty, __S>
+                for #name #ty_generics #serialize_where {
+                    #[inline]
+                    fn serialize_with(
+                        field: &#from_ty,
+                        serializer: &mut __S,
+                    ) -> Result<Self::Resolver, <__S as Fallible>::Error> {
+                        let mirror: Self = #into(field);
+                        let resolver = Serialize::<__S>::serialize(&mirror, serializer)?;
+
+                        Ok((mirror, resolver))
+                    }
+                }
+            }
+        })
+        .collect();
+
+    (archive_impls, serialize_impls)
+}
+
+/// Checks at compile time that the remote type `from_ty` consists of exactly
+/// the mirrored fields and variants, and that the types of the fields match.
+fn exhaustive_check(
+    input: &DeriveInput,
+    data_attrs: &DataAttributes<'_>,
+    from_ty: &Type,
+    omitted: &[(Ident, Path)],
+    fallback: bool,
+) -> TokenStream {
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+
+    let check = match input.data {
+        Data::Struct(ref data) => {
+            let (pattern, types) = exhaustive_pattern(&data.fields, &data_attrs.fields, omitted);
+
+            let pattern = quote_spanned! {from_ty.span()=> #from_ty { #pattern } };
+
+            quote! {
+                let #pattern = field;
+                #types
+            }
+        }
+        Data::Enum(ref data) => {
+            let enum_path = enum_path(from_ty);
+            let mut fallback = fallback;
+            let mut arms = Vec::with_capacity(data.variants.len());
+            let mut field_attrs = data_attrs.fields.as_slice();
+
+            for &(variant, ref variant_attrs) in data_attrs.variants.iter() {
+                let (attrs, rest) = field_attrs.split_at(variant.fields.len());
+                field_attrs = rest;
+
+                let (pattern, types) = exhaustive_pattern(&variant.fields, attrs, &[]);
+                let remote_variants = variant_attrs.remote_variants(&variant.ident);
+                fallback |= variant_attrs.other;
+
+                if remote_variants.is_empty() {
+                    continue;
+                }
+
+                let patterns = remote_variants.iter().map(|remote| {
+                    quote_spanned! {from_ty.span()=> #enum_path::#remote { #pattern } }
+                });
+
+                arms.push(quote! { #( #patterns )|* => { #types } });
+            }
+
+            // Unknown variants are not mapped on purpose
+            let fallback = fallback.then(|| quote! { _ => {} });
+
+            quote_spanned! {from_ty.span()=>
+                match field {
+                    #( #arms )*
+                    #fallback
+                }
+            }
+        }
+        Data::Union(_) => TokenStream::new(),
+    };
+
+    quote! {
+        const _: () = {
+            #[allow(dead_code, unused_variables)]
+            fn exhaustive #impl_generics (field: &#from_ty) #where_clause {
+                fn remote_type<T: ?Sized>(_: &T) -> PhantomData<T> {
+                    PhantomData
+                }
+
+                #check
+            }
+        };
+    }
+}
+
+/// Destructuring pattern over the remote fields of `fields`, alongside type
+/// assertions for all of them.
+///
+/// Fields with a getter are not named since the remote fields that they are
+/// computed from might be private. The pattern only ends with `..` if there
+/// are such fields.
+fn exhaustive_pattern(
+    fields: &Fields,
+    attrs: &[(&Field, ParsedAttributes)],
+    omitted: &[(Ident, Path)],
+) -> (TokenStream, TokenStream) {
+    let mut pattern = TokenStream::new();
+    let mut types = TokenStream::new();
+    let mut rest = false;
+
+    for (i, (&(field, ref attrs), member)) in attrs.iter().zip(fields.members()).enumerate() {
+        if attrs.getter.is_some() {
+            rest = true;
+
+            continue;
+        }
+
+        let remote = attrs.remote_member(member);
+        let ty = attrs.from.as_ref().unwrap_or(&field.ty);
+        let binding = format_ident!("__{i}", span = ty.span());
+        pattern.extend(quote! { #remote: #binding, });
+
+        // Separate statements so that the remote type is inferred without
+        // the mirror's type being expected already
+        types.extend(quote_spanned! {ty.span()=>
+            let #binding = remote_type(#binding);
+            let _: PhantomData<#ty> = #binding;
+        });
+    }
+
+    for (name, _) in omitted {
+        pattern.extend(quote! { #name: _, });
+    }
+
+    if rest {
+        pattern.extend(quote! { .. });
+    }
+
+    (pattern, types)
+}
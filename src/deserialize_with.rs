@@ -1,324 +1,857 @@
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
 use syn::{
-    parse_quote, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Error, Fields,
-    Generics, Ident, Index, Result,
+    parse_quote, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Error, Expr, Field,
+    Fields, Ident, Member, Path, Result, Token, Type, WhereClause,
 };
 
-use crate::util::{parse_top_attrs, with_inner, with_ty};
+use crate::{
+    util::{
+        add_param, alias_generics, deserialize_field, enum_path, field_bounds, fn_wrapper_defs,
+        is_borrowed, parse_top_attrs, validate_attrs, validate_transparent, BoundKind, Bounds,
+        DataAttributes, Errors, FromMirror, ParsedAttributes, TopAttributes,
+    },
+    version::Version,
+};
 
 pub fn derive(mut input: DeriveInput) -> Result<TokenStream> {
     let _ = input.generics.make_where_clause();
     let (_, ty_generics, where_clause) = input.generics.split_for_impl();
     let where_clause = where_clause.unwrap();
 
-    let mut impl_input_params = Punctuated::default();
-    impl_input_params.push(parse_quote! { __D: Fallible + ?Sized });
-
-    for param in input.generics.params.iter() {
-        impl_input_params.push(param.clone());
-    }
-
-    let impl_input_generics = Generics {
-        lt_token: Some(Default::default()),
-        params: impl_input_params,
-        gt_token: Some(Default::default()),
-        where_clause: input.generics.where_clause.clone(),
-    };
-
+    let impl_input_generics = add_param(&input.generics, parse_quote! { __D: Fallible + ?Sized });
     let (impl_generics, _, _) = impl_input_generics.split_for_impl();
-
-    let from_tys = parse_top_attrs(&input.attrs)?;
-
-    if from_tys.is_empty() {
-        let msg = "requires top level attribute `#[archive_with(from(TypeName))]`";
-
-        return Err(Error::new(Span::call_site(), msg));
-    }
-
+    let alias_generics = alias_generics(&input.generics);
+
+    let mut errors = Errors::default();
+    let top_attrs = parse_top_attrs(&input.attrs, &mut errors);
+
+    let TopAttributes {
+        from: ref from_tys,
+        version,
+        ref crate_path,
+        ref bound,
+        ref from_mirror,
+        ..
+    } = top_attrs;
+
+    let data_attrs = validate_attrs(&input.data, version, &mut errors);
+    validate(&input, &top_attrs, &data_attrs, &mut errors);
+    errors.finish()?;
+
+    // Without errors, the attributes of all fields and variants are parsed
+    let field_attrs = &data_attrs.fields;
     let name = &input.ident;
 
-    let deserialize_impl: TokenStream = match input.data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let mut deserialize_where = where_clause.clone();
+    // Field attributes are ignored when converting through `from_mirror`
+    let (deserialize_impl, fn_wrappers) = if let Some(from_mirror) = from_mirror {
+        let deserialize_impl = proxy(&input, from_tys, from_mirror, bound, version);
 
-                for field in fields.named.iter() {
-                    let (ty, _) = with_ty(field)?;
+        (deserialize_impl, TokenStream::new())
+    } else {
+        let mut borrowed = false;
 
-                    deserialize_where
-                        .predicates
-                        .push(parse_quote! { #ty: Archive });
+        for (field, attrs) in data_attrs.fields.iter() {
+            borrowed |= is_borrowed(field, attrs)?;
+        }
 
-                    deserialize_where
-                        .predicates
-                        .push(parse_quote! { Archived<#ty>: Deserialize<#ty, __D> });
+        let deserialize_impl: TokenStream = match input.data {
+            Data::Struct(ref data) => match data.fields {
+                Fields::Named(_) | Fields::Unnamed(_) => {
+                    let mut deserialize_where = where_clause.clone();
+                    let deserialize_bounds =
+                        field_bounds(BoundKind::Deserialize, bound, field_attrs, version)?;
+                    deserialize_where.predicates.extend(deserialize_bounds);
+
+                    let values = field_attrs
+                        .iter()
+                        .zip(data.fields.members())
+                        .map(|(&(field, ref attrs), member)| {
+                            let archived = parse_quote! { &field.#member };
+
+                            deserialize_field(field, attrs, archived, version)
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+
+                    from_tys
+                        .iter()
+                        .map(|from_ty| {
+                            let construct = construct(
+                                quote!(#from_ty),
+                                &data.fields,
+                                field_attrs,
+                                values.clone(),
+                                Some(&top_attrs),
+                            )?;
+
+                            let body = quote! { Ok(#construct) };
+
+                            Ok(deserialize_impl(
+                                &input,
+                                from_ty,
+                                &deserialize_where,
+                                borrowed,
+                                body,
+                            ))
+                        })
+                        .collect::<Result<_>>()?
                 }
-
-                let deserialize_fields: Vec<_> = fields
-                    .named
-                    .iter()
-                    .map(|field| {
-                        let name = &field.ident;
-                        let (ty, attrs) = with_ty(field).unwrap();
-
-                        let value = with_inner(
-                            field,
-                            &attrs,
-                            parse_quote! {
-                                Deserialize::<#ty, __D>::deserialize(
-                                    &field.#name,
-                                    deserializer,
-                                )?
-                            },
-                        )
-                        .unwrap();
-
-                        quote! { #name: #value }
-                    })
-                    .collect();
-
-                from_tys
+                Fields::Unit => from_tys
                     .iter()
                     .map(|from_ty| {
                         quote! {
                             impl #impl_generics
                             DeserializeWith<<Self as Archive>::Archived, #from_ty, __D>
-                            for #name #ty_generics #deserialize_where {
+                            for #name #ty_generics #where_clause {
                                 #[inline]
                                 fn deserialize_with(
-                                    field: &<Self as Archive>::Archived,
-                                    deserializer: &mut __D
+                                    _: &<Self as Archive>::Archived,
+                                    _: &mut __D
                                 ) -> Result<#from_ty, <__D as Fallible>::Error> {
-                                    Ok(#from_ty {
-                                        #( #deserialize_fields, )*
-                                    })
+                                    Ok(#from_ty)
                                 }
                             }
                         }
                     })
-                    .collect()
-            }
-            Fields::Unnamed(ref fields) => {
+                    .collect(),
+            },
+            Data::Enum(ref data) => {
                 let mut deserialize_where = where_clause.clone();
 
-                for field in fields.unnamed.iter() {
-                    let (ty, _) = with_ty(field)?;
+                let deserialize_bounds =
+                    field_bounds(BoundKind::Deserialize, bound, field_attrs, version)?;
+                deserialize_where.predicates.extend(deserialize_bounds);
 
-                    deserialize_where
-                        .predicates
-                        .push(parse_quote! { #ty: Archive });
-
-                    deserialize_where
-                        .predicates
-                        .push(parse_quote! { Archived<#ty>: Deserialize<#ty, __D> });
-                }
-
-                from_tys
+                // Index of each variant's first field among the fields of all variants
+                let offsets: Vec<usize> = data
+                    .variants
                     .iter()
-                    .map(|from_ty| {
-                        let deserialize_fields =
-                            fields.unnamed.iter().enumerate().map(|(i, field)| {
-                                let index = Index::from(i);
-                                let (ty, attrs) = with_ty(field).unwrap();
-
-                                let value = with_inner(
-                                    field,
-                                    &attrs,
-                                    parse_quote! {
-                                        Deserialize::<#ty, __D>::deserialize(
-                                            &field.#index,
-                                            deserializer,
-                                        )?
-                                    },
-                                )
-                                .unwrap();
-
-                                quote! { #value }
-                            });
+                    .scan(0, |offset, v| {
+                        let start = *offset;
+                        *offset += v.fields.len();
 
-                        quote! {
-                            impl #impl_generics
-                            DeserializeWith<<Self as Archive>::Archived, #from_ty, __D>
-                            for #name #ty_generics #deserialize_where {
-                                #[inline]
-                                fn deserialize_with(
-                                    field: &<Self as Archive>::Archived,
-                                    deserializer: &mut __D
-                                ) -> Result<#from_ty, <__D as Fallible>::Error> {
-                                    Ok(#from_ty(
-                                        #( #deserialize_fields, )*
-                                    ))
-                                }
-                            }
-                        }
+                        Some(start)
                     })
-                    .collect()
-            }
-            Fields::Unit => from_tys
+                    .collect();
+
+                from_tys
                 .iter()
                 .map(|from_ty| {
-                    quote! {
-                        impl #impl_generics
-                        DeserializeWith<<Self as Archive>::Archived, #from_ty, __D>
-                        for #name #ty_generics #where_clause {
-                            #[inline]
-                            fn deserialize_with(
-                                _: &<Self as Archive>::Archived,
-                                _: &mut __D
-                            ) -> Result<#from_ty, <__D as Fallible>::Error> {
-                                Ok(#from_ty)
-                            }
-                        }
-                    }
+                    let enum_path = enum_path(from_ty);
+
+                    let deserialize_variants = data_attrs.variants.iter().zip(&offsets).map(|(&(v, ref variant_attrs), &offset)| {
+                        let variant = &v.ident;
+                        let attrs = &field_attrs[offset..offset + v.fields.len()];
+
+                        let bindings: Vec<_> = v
+                            .fields
+                            .iter()
+                            .enumerate()
+                            .map(|(i, field)| {
+                                field.ident.clone().unwrap_or_else(|| {
+                                    Ident::new(&format!("_{}", i), field.span())
+                                })
+                            })
+                            .collect();
+
+                        let values = attrs
+                            .iter()
+                            .zip(&bindings)
+                            .map(|(&(field, ref attrs), binding)| {
+                                deserialize_field(field, attrs, parse_quote! { #binding }, version)
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+
+                        let remote_variant = variant_attrs.remote_variant(variant);
+
+                        let construct = construct(
+                            quote!(#enum_path::#remote_variant),
+                            &v.fields,
+                            attrs,
+                            values,
+                            None,
+                        )?;
+
+                        let arm = match v.fields {
+                            Fields::Named(_) => quote! {
+                                __SelfArchived::#variant { #( #bindings, )* } => #construct
+                            },
+                            Fields::Unnamed(_) => quote! {
+                                __SelfArchived::#variant( #( #bindings, )* ) => #construct
+                            },
+                            Fields::Unit => quote! { __SelfArchived::#variant => #construct },
+                        };
+
+                        Ok(arm)
+                    });
+
+                    let deserialize_variants = deserialize_variants.collect::<Result<Vec<_>>>()?;
+
+                    let body = quote! {
+                        type __SelfArchived #alias_generics = <#name #ty_generics as Archive>::Archived;
+
+                        Ok(match field {
+                            #( #deserialize_variants, )*
+                        })
+                    };
+
+                    Ok(deserialize_impl(&input, from_ty, &deserialize_where, borrowed, body))
                 })
-                .collect(),
-        },
-        Data::Enum(ref data) => {
-            let mut deserialize_where = where_clause.clone();
-
-            for variant in data.variants.iter() {
-                match variant.fields {
-                    Fields::Named(ref fields) => {
-                        for field in fields.named.iter() {
-                            let (ty, _) = with_ty(field)?;
-
-                            deserialize_where
-                                .predicates
-                                .push(parse_quote! { #ty: Archive });
-
-                            deserialize_where
-                                .predicates
-                                .push(parse_quote! { Archived<#ty>: Deserialize<#ty, __D> });
-                        }
-                    }
-                    Fields::Unnamed(ref fields) => {
-                        for field in fields.unnamed.iter() {
-                            let (ty, _) = with_ty(field)?;
+                .collect::<Result<_>>()?
+            }
+            Data::Union(_) => unreachable!("unions are rejected during validation"),
+        };
 
-                            deserialize_where
-                                .predicates
-                                .push(parse_quote! { #ty: Archive });
+        (
+            deserialize_impl,
+            fn_wrapper_defs(&input, &data_attrs, true, version),
+        )
+    };
 
-                            deserialize_where
-                                .predicates
-                                .push(parse_quote! { Archived<#ty>: Deserialize<#ty, __D> });
-                        }
-                    }
-                    Fields::Unit => {}
+    let fallible = version.fallible();
+
+    let tokens = quote! {
+        #[automatically_derived]
+        const _: () = {
+            use #crate_path as __rkyv;
+            use #fallible;
+            use __rkyv::{Archive, Archived, Deserialize, with::DeserializeWith};
+
+            #fn_wrappers
+
+            #deserialize_impl
+        };
+    };
+
+    Ok(tokens)
+}
+
+/// Checks the top level attributes against the shape of the type.
+fn validate(
+    input: &DeriveInput,
+    top_attrs: &TopAttributes,
+    data_attrs: &DataAttributes<'_>,
+    errors: &mut Errors,
+) {
+    let TopAttributes {
+        ref from,
+        ref constructor,
+        ref default,
+        ref omitted,
+        ref into,
+        ref from_mirror,
+        ref transparent,
+        version,
+        ..
+    } = *top_attrs;
+
+    if from.is_empty() {
+        let msg = "requires top level attribute `#[archive_with(from(TypeName))]`";
+        errors.push(Error::new_spanned(&input.ident, msg));
+    }
+
+    for (_, attrs) in data_attrs.fields.iter() {
+        if let (Some(serialize_fn), None) = (&attrs.serialize_fn, &attrs.deserialize_fn) {
+            let msg =
+                "`DeserializeWith` requires `deserialize_fn = \"...\"` alongside `serialize_fn`";
+            errors.push(Error::new_spanned(serialize_fn, msg));
+        }
+    }
+
+    if let Some(transparent) = transparent {
+        validate_transparent(&input.data, transparent, errors);
+
+        if let Some(from_mirror) = from_mirror {
+            let msg = "`from_mirror` cannot be combined with `transparent`";
+            errors.push(Error::new_spanned(&from_mirror.path, msg));
+        }
+
+        if let Some(ref default) = default {
+            let msg = "`default` cannot be combined with `transparent`";
+            errors.push(Error::new_spanned(&default.key, msg));
+        }
+
+        if let Some((name, _)) = omitted.first() {
+            let msg = "`omitted` cannot be combined with `transparent`";
+            errors.push(Error::new_spanned(name, msg));
+        }
+
+        if let Data::Struct(_) = input.data {
+            for (_, attrs) in data_attrs.fields.iter() {
+                if let Some(ref setter) = attrs.setter {
+                    let msg = "`setter` cannot be combined with `transparent`";
+                    errors.push(Error::new_spanned(&setter.path, msg));
+                }
+
+                if let Some(ref path) = attrs.path {
+                    let msg = "`path` cannot be combined with `transparent`";
+                    errors.push(Error::new_spanned(path, msg));
                 }
             }
+        }
+    }
 
-            from_tys
-                .iter()
-                .map(|from_ty| {
-                    let deserialize_variants = data.variants.iter().map(|v| {
-                        let variant = &v.ident;
+    if let Some(from_mirror) = from_mirror {
+        if let Some(ref constructor) = constructor {
+            let msg = "`constructor` cannot be combined with `from_mirror`";
+            errors.push(Error::new_spanned(constructor, msg));
+        }
 
-                        match v.fields {
-                            Fields::Named(ref fields) => {
-                                let bindings = fields.named.iter().map(|field| {
-                                    let name = &field.ident;
-
-                                    quote!(#name)
-                                });
-
-                                let fields = fields.named.iter().map(|field| {
-                                    let name = &field.ident;
-                                    let (ty, attrs) = with_ty(field).unwrap();
-                                    let value = with_inner(
-                                        field,
-                                        &attrs,
-                                        parse_quote! {
-                                            Deserialize::<#ty, __D>::deserialize(
-                                                #name,
-                                                deserializer,
-                                            )?
-                                        },
-                                    )
-                                    .unwrap();
-
-                                    quote! { #name: #value }
-                                });
-
-                                quote! {
-                                    __SelfArchived::#variant { #( #bindings, )* } => {
-                                        #from_ty::#variant { #( #fields, )* }
-                                    }
-                                }
-                            }
-                            Fields::Unnamed(ref fields) => {
-                                let bindings = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                                    let name = Ident::new(&format!("_{}", i), f.span());
-
-                                    quote!(#name)
-                                });
-
-                                let fields = fields.unnamed.iter().enumerate().map(|(i, field)| {
-                                    let binding = Ident::new(&format!("_{}", i), field.span());
-                                    let (ty, attrs) = with_ty(field).unwrap();
-
-                                    let value = with_inner(
-                                        field,
-                                        &attrs,
-                                        parse_quote! {
-                                            Deserialize::<#ty, __D>::deserialize(
-                                                #binding,
-                                                deserializer,
-                                            )?
-                                        },
-                                    )
-                                    .unwrap();
-
-                                    quote! { #value }
-                                });
-
-                                quote! {
-                                    __SelfArchived::#variant( #( #bindings, )* ) =>
-                                        #from_ty::#variant(#( #fields, )*)
-                                }
-                            }
-                            Fields::Unit => {
-                                quote! { __SelfArchived::#variant => #from_ty::#variant }
-                            }
-                        }
-                    });
+        if let Some(ref default) = default {
+            let msg = "`default` cannot be combined with `from_mirror`";
+            errors.push(Error::new_spanned(&default.key, msg));
+        }
 
-                    quote! {
-                        impl #impl_generics
-                        DeserializeWith<<Self as Archive>::Archived, #from_ty, __D>
-                        for #name #ty_generics #deserialize_where {
-                            #[inline]
-                            fn deserialize_with(
-                                field: &<Self as Archive>::Archived,
-                                deserializer: &mut __D
-                            ) -> ::core::result::Result<#from_ty, __D::Error> {
-                                type __SelfArchived #ty_generics = <#name #ty_generics as Archive>::Archived;
-
-                                Ok(match field {
-                                    #( #deserialize_variants, )*
-                                })
-                            }
-                        }
-                    }
-                })
-                .collect()
+        if let Some((name, _)) = omitted.first() {
+            let msg = "`omitted` cannot be combined with `from_mirror`";
+            errors.push(Error::new_spanned(name, msg));
+        }
+
+        if let Some(None) = from_mirror.fallible {
+            if !version.is_v0_8() {
+                let msg = "rkyv 0.7 requires `from_mirror_error = \"...\"` for `try_from_mirror`";
+                errors.push(Error::new_spanned(&from_mirror.path, msg));
+            }
+        }
+    } else {
+        if let Some(into) = into {
+            let msg = "`DeserializeWith` requires `from_mirror = \"...\"` or \
+                `try_from_mirror = \"...\"` alongside `into`";
+            errors.push(Error::new_spanned(into, msg));
+        }
+
+        let mut borrowed = false;
+
+        for (field, attrs) in data_attrs.fields.iter() {
+            match is_borrowed(field, attrs) {
+                Ok(is_borrowed) => borrowed |= is_borrowed,
+                Err(err) => errors.push(err),
+            }
+        }
+
+        if let (true, [_, second, ..]) = (borrowed, from.as_slice()) {
+            let msg = "remote types that borrow from the archive only support a single `from` type";
+            errors.push(Error::new_spanned(second, msg));
+        }
+    }
+
+    for (_, attrs) in data_attrs.fields.iter() {
+        let Some(ref arg) = attrs.arg else {
+            continue;
+        };
+
+        if constructor.is_none() {
+            let msg = "`arg` requires `constructor = \"...\"`";
+            errors.push(Error::new_spanned(arg, msg));
+        }
+
+        // Fields with a setter are not passed to the constructor
+        if attrs.setter.is_some() {
+            let msg = "`arg` cannot be combined with `setter`";
+            errors.push(Error::new_spanned(arg, msg));
+        }
+    }
+
+    match input.data {
+        Data::Struct(_) => {
+            if constructor.is_some() {
+                if let Some(ref default) = default {
+                    let msg = "`default` cannot be combined with `constructor`";
+                    errors.push(Error::new_spanned(&default.key, msg));
+                }
+
+                if let Some((name, _)) = omitted.first() {
+                    let msg = "`omitted` cannot be combined with `constructor`";
+                    errors.push(Error::new_spanned(name, msg));
+                }
+            }
+        }
+        Data::Enum(_) => {
+            if let Some(ref constructor) = constructor {
+                let msg = "`constructor` is only supported for structs";
+                errors.push(Error::new_spanned(constructor, msg));
+            }
+
+            if let Some(ref default) = default {
+                let msg = "`default` is only supported for structs";
+                errors.push(Error::new_spanned(&default.key, msg));
+            }
+
+            if let Some((name, _)) = omitted.first() {
+                let msg = "`omitted` is only supported for structs";
+                errors.push(Error::new_spanned(name, msg));
+            }
+
+            for (_, attrs) in data_attrs.fields.iter() {
+                if let Some(ref setter) = attrs.setter {
+                    let msg = "`setter` is only supported for structs";
+                    errors.push(Error::new_spanned(&setter.path, msg));
+                }
+            }
         }
         Data::Union(_) => {
             let msg = "DeserializeWith cannot be derived for unions";
+            errors.push(Error::new_spanned(input, msg));
+        }
+    }
+}
+
+/// Creates the remote type `from_ty` through `body` which has the archived
+/// value as `field` and the `deserializer` in scope.
+///
+/// The output of `DeserializeWith` is not tied to the lifetime of the archived
+/// value so remote types that borrow from the archive get an inherent
+/// `from_archived` method instead.
+fn deserialize_impl(
+    input: &DeriveInput,
+    from_ty: &Type,
+    deserialize_where: &WhereClause,
+    borrowed: bool,
+    body: TokenStream,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    if !borrowed {
+        let impl_input_generics =
+            add_param(&input.generics, parse_quote! { __D: Fallible + ?Sized });
+        let (impl_generics, _, _) = impl_input_generics.split_for_impl();
+
+        return quote! {
+            impl #impl_generics
+            DeserializeWith<<Self as Archive>::Archived, #from_ty, __D>
+            for #name #ty_generics #deserialize_where {
+                #[inline]
+                fn deserialize_with(
+                    field: &<Self as Archive>::Archived,
+                    deserializer: &mut __D
+                ) -> Result<#from_ty, <__D as Fallible>::Error> {
+                    #body
+                }
+            }
+        };
+    }
 
-            return Err(Error::new_spanned(input, msg));
+    let vis = &input.vis;
+    let mut from_archived_where = deserialize_where.clone();
+
+    // The archive must outlive everything that the remote type borrows
+    for param in input.generics.lifetimes() {
+        let lifetime = &param.lifetime;
+        from_archived_where
+            .predicates
+            .push(parse_quote! { '__archived: #lifetime });
+    }
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Creates the remote type out of the archived value, borrowing
+            /// its `&str` and `&[T]` fields from the archive.
+            #[inline]
+            #vis fn from_archived<'__archived, __D: Fallible + ?Sized>(
+                field: &'__archived <Self as Archive>::Archived,
+                deserializer: &mut __D
+            ) -> Result<#from_ty, <__D as Fallible>::Error> #from_archived_where {
+                #body
+            }
         }
+    }
+}
+
+/// Deserializes the mirror through its own `Deserialize` implementation and
+/// converts it into the remote types through `from_mirror`.
+fn proxy(
+    input: &DeriveInput,
+    from_tys: &[Type],
+    from_mirror: &FromMirror,
+    bound: &Bounds,
+    version: Version,
+) -> TokenStream {
+    let name = &input.ident;
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let impl_input_generics = add_param(&input.generics, parse_quote! { __D: Fallible + ?Sized });
+    let (impl_generics, _, _) = impl_input_generics.split_for_impl();
+
+    let mut deserialize_where = where_clause.unwrap().clone();
+
+    match bound.get(BoundKind::Deserialize) {
+        Some(bounds) => deserialize_where.predicates.extend(bounds.iter().cloned()),
+        None => deserialize_where.predicates.push(parse_quote! {
+            Archived<#name #ty_generics>: Deserialize<#name #ty_generics, __D>
+        }),
+    }
+
+    let FromMirror {
+        ref path,
+        ref fallible,
+    } = *from_mirror;
+
+    let convert = match fallible {
+        Some(error_ty) => {
+            let (error, bounds) =
+                version.error_into(&parse_quote!(__D), parse_quote!(err), error_ty.as_ref());
+            deserialize_where.predicates.extend(bounds);
+
+            quote! { #path(mirror).map_err(|err| #error) }
+        }
+        None => quote! { Ok(#path(mirror)) },
     };
 
-    let tokens = quote! {
-        #[automatically_derived]
-        const _: () = {
-            use ::rkyv::{Archive, Archived, Deserialize, Fallible};
+    from_tys
+        .iter()
+        .map(|from_ty| {
+            quote! {
+                impl #impl_generics
+                DeserializeWith<<Self as Archive>::Archived, #from_ty, __D>
+                for #name #ty_generics #deserialize_where {
+                    #[inline]
+                    fn deserialize_with(
+                        field: &<Self as Archive>::Archived,
+                        deserializer: &mut __D
+                    ) -> Result<#from_ty, <__D as Fallible>::Error> {
+                        let mirror: Self = Deserialize::<Self, __D>::deserialize(field, deserializer)?;
+
+                        #convert
+                    }
+                }
+            }
+        })
+        .collect()
+}
 
-            #deserialize_impl
+/// Creates the remote type at `path` out of the deserialized values of its
+/// fields, either directly or through the given constructor.
+///
+/// The top level attributes are only given for structs. If any field has a
+/// setter, or a `path` alongside a `constructor` or `default`, the remote type
+/// is created from a base value instead onto which all fields are applied.
+/// Otherwise, the levels of `path`s are created through nested struct
+/// literals.
+fn construct(
+    path: TokenStream,
+    fields: &Fields,
+    attrs: &[(&Field, ParsedAttributes)],
+    values: Vec<Expr>,
+    top_attrs: Option<&TopAttributes>,
+) -> Result<TokenStream> {
+    let (constructor, default, omitted, transparent) = match top_attrs {
+        Some(top_attrs) => (
+            top_attrs.constructor.as_ref(),
+            top_attrs.default.as_ref(),
+            top_attrs.omitted.as_slice(),
+            top_attrs.transparent.is_some(),
+        ),
+        None => (None, None, [].as_slice(), false),
+    };
+
+    if transparent && constructor.is_none() {
+        // Validation ensured that there is exactly one field
+        let value = &values[0];
+
+        return Ok(quote! { ::core::convert::From::from(#value) });
+    }
+
+    let setters = attrs.iter().any(|(_, attrs)| attrs.setter.is_some());
+    let paths = attrs.iter().any(|(_, attrs)| attrs.path.is_some());
+
+    if setters || (paths && (constructor.is_some() || default.is_some())) {
+        let base = match (constructor, default) {
+            (Some(constructor), _) => Base::Constructor(constructor),
+            (None, Some(default)) => Base::Value(default.make_expr()),
+            (None, None) => Base::Value(parse_quote! { ::core::default::Default::default() }),
         };
+
+        return construct_with_setters(path, fields, attrs, values, base, omitted);
+    }
+
+    if paths {
+        return construct_nested(path, fields, attrs, values, omitted);
+    }
+
+    if let Some(constructor) = constructor {
+        let args = constructor_args(attrs.iter().map(|(_, attrs)| attrs).zip(values).collect())?;
+
+        return Ok(quote! { #constructor( #( #args ),* ) });
+    }
+
+    let construct = match fields {
+        Fields::Named(_) | Fields::Unnamed(_) => {
+            let members = remote_members(fields, attrs);
+            let omitted_names = omitted.iter().map(|(name, _)| name);
+            let omitted_values = omitted.iter().map(|(_, function)| function);
+            let default = default.map(|default| default.make_expr()).into_iter();
+
+            quote! {
+                #path {
+                    #( #members: #values, )*
+                    #( #omitted_names: #omitted_values(), )*
+                    #( ..#default )*
+                }
+            }
+        }
+        Fields::Unit => path,
     };
 
-    Ok(tokens)
+    Ok(construct)
+}
+
+/// How the base value is created onto which setters are applied.
+enum Base<'a> {
+    /// Called with all fields that don't have a setter.
+    Constructor(&'a Path),
+    Value(Expr),
+}
+
+/// Creates the base value and then applies the fields onto it.
+///
+/// Fields with a setter are applied through it and fields with a `path` are
+/// assigned to it. All others are either passed to the constructor or
+/// assigned directly.
+fn construct_with_setters(
+    path: TokenStream,
+    fields: &Fields,
+    attrs: &[(&Field, ParsedAttributes)],
+    values: Vec<Expr>,
+    base: Base<'_>,
+    omitted: &[(Ident, Path)],
+) -> Result<TokenStream> {
+    let remote = Ident::new("__remote", Span::call_site());
+    let mut applied = Vec::new();
+    let mut args = Vec::new();
+
+    let members = remote_members(fields, attrs);
+    let iter = attrs.iter().zip(members).zip(values);
+
+    for (((_, attrs), member), value) in iter {
+        if let Some(ref setter) = attrs.setter {
+            applied.push(setter.make_expr(&remote, &value));
+        } else if let Some(ref path) = attrs.path {
+            applied.push(parse_quote! { #remote.#path = #value });
+        } else if let Base::Constructor(_) = base {
+            args.push((attrs, value));
+        } else {
+            applied.push(parse_quote! { #remote.#member = #value });
+        }
+    }
+
+    for (name, function) in omitted {
+        applied.push(parse_quote! { #remote.#name = #function() });
+    }
+
+    let base = match base {
+        Base::Constructor(constructor) => {
+            let args = constructor_args(args)?;
+
+            quote! { #constructor( #( #args ),* ) }
+        }
+        Base::Value(value) => quote! { #value },
+    };
+
+    Ok(quote! {{
+        let mut #remote: #path = #base;
+        #( #applied; )*
+
+        #remote
+    }})
+}
+
+/// A level of the remote type that is created through a struct literal.
+struct Level {
+    /// Type of the level, which is only unknown if none of the `path`s that
+    /// contain it specify `nested(...)`.
+    ty: Option<TokenStream>,
+    /// Path to the level within the remote type, used for error messages.
+    path: Punctuated<Member, Token![.]>,
+    fields: Vec<(Member, LevelValue)>,
+}
+
+enum LevelValue {
+    Value(Expr),
+    Level(Level),
+}
+
+impl Level {
+    /// The level within this one at `member`, created if it doesn't exist yet.
+    fn nested(&mut self, member: &Member) -> &mut Level {
+        let pos = self
+            .fields
+            .iter()
+            .position(|(nested, value)| nested == member && matches!(value, LevelValue::Level(_)));
+
+        let pos = pos.unwrap_or_else(|| {
+            let mut path = self.path.clone();
+            path.push(member.clone());
+
+            let level = Level {
+                ty: None,
+                path,
+                fields: Vec::new(),
+            };
+
+            self.fields.push((member.clone(), LevelValue::Level(level)));
+
+            self.fields.len() - 1
+        });
+
+        match self.fields[pos].1 {
+            LevelValue::Level(ref mut level) => level,
+            LevelValue::Value(_) => unreachable!(),
+        }
+    }
+
+    /// Sets the type of the level, unless another `path` already specified a
+    /// different one.
+    fn set_ty(&mut self, ty: &Type) -> Result<()> {
+        let tokens = quote!(#ty);
+
+        match self.ty {
+            Some(ref prev) if prev.to_string() != tokens.to_string() => {
+                let msg = format!("conflicting types for `{}`", self.display_path());
+
+                Err(Error::new_spanned(ty, msg))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.ty = Some(tokens);
+
+                Ok(())
+            }
+        }
+    }
+
+    /// The path to the level as written within `path = "..."`.
+    fn display_path(&self) -> String {
+        let path = &self.path;
+
+        quote!(#path).to_string().replace(' ', "")
+    }
+
+    fn into_tokens(self) -> Result<TokenStream> {
+        let Some(ty) = self.ty else {
+            let msg = format!(
+                "`DeserializeWith` requires the type of `{}` through `nested(...)`, or a top \
+                level `default` or `constructor` to assign the nested fields to",
+                self.display_path()
+            );
+
+            return Err(Error::new_spanned(&self.path, msg));
+        };
+
+        let (members, values): (Vec<_>, Vec<_>) = self.fields.into_iter().unzip();
+
+        let values = values
+            .into_iter()
+            .map(|value| match value {
+                LevelValue::Value(value) => Ok(quote!(#value)),
+                LevelValue::Level(level) => level.into_tokens(),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(quote! { #ty { #( #members: #values, )* } })
+    }
+}
+
+/// Creates the remote type and all levels of the fields' `path`s through
+/// nested struct literals.
+///
+/// The types of the levels are taken from the `nested(...)` of any field
+/// whose `path` contains them.
+fn construct_nested(
+    path: TokenStream,
+    fields: &Fields,
+    attrs: &[(&Field, ParsedAttributes)],
+    values: Vec<Expr>,
+    omitted: &[(Ident, Path)],
+) -> Result<TokenStream> {
+    let mut root = Level {
+        ty: Some(path),
+        path: Punctuated::new(),
+        fields: Vec::new(),
+    };
+
+    let members = remote_members(fields, attrs);
+
+    for ((member, (_, attrs)), value) in members.into_iter().zip(attrs).zip(values) {
+        let Some(ref field_path) = attrs.path else {
+            root.fields.push((member, LevelValue::Value(value)));
+
+            continue;
+        };
+
+        let mut members: Vec<_> = field_path.iter().cloned().collect();
+        // Parsing ensures that the path is not empty
+        let last = members.pop().unwrap();
+        let mut nested = attrs.nested.iter().flatten();
+        let mut level = &mut root;
+
+        for member in members.iter() {
+            level = level.nested(member);
+
+            if let Some(ty) = nested.next() {
+                level.set_ty(ty)?;
+            }
+        }
+
+        level.fields.push((last, LevelValue::Value(value)));
+    }
+
+    for (name, function) in omitted {
+        let value = parse_quote! { #function() };
+        root.fields
+            .push((Member::Named(name.clone()), LevelValue::Value(value)));
+    }
+
+    root.into_tokens()
+}
+
+/// The members of the remote type that correspond to the fields.
+fn remote_members(fields: &Fields, attrs: &[(&Field, ParsedAttributes)]) -> Vec<Member> {
+    fields
+        .members()
+        .zip(attrs)
+        .map(|(member, (_, attrs))| attrs.remote_member(member))
+        .collect()
+}
+
+/// Orders the values as constructor arguments.
+///
+/// Fields with `arg = N` take the `N`th argument, all others fill the
+/// remaining arguments in field order.
+fn constructor_args(fields: Vec<(&ParsedAttributes, Expr)>) -> Result<Vec<Expr>> {
+    let len = fields.len();
+    let mut args = vec![None; len];
+    let mut implicit = Vec::new();
+
+    for (attrs, value) in fields {
+        let Some(ref arg) = attrs.arg else {
+            implicit.push(value);
+
+            continue;
+        };
+
+        match args.get_mut(arg.base10_parse::<usize>()?) {
+            Some(slot @ None) => *slot = Some(value),
+            Some(Some(_)) => {
+                let msg = "argument position is already taken by another field";
+
+                return Err(Error::new_spanned(arg, msg));
+            }
+            None => {
+                let msg = format!("argument position must be less than {len}");
+
+                return Err(Error::new_spanned(arg, msg));
+            }
+        }
+    }
+
+    let mut implicit = implicit.into_iter();
+
+    // There are exactly as many empty slots as implicit values
+    let args = args
+        .into_iter()
+        .map(|arg| arg.or_else(|| implicit.next()).unwrap())
+        .collect();
+
+    Ok(args)
 }
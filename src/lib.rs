@@ -6,6 +6,7 @@ use syn::{parse_macro_input, DeriveInput};
 mod archive_with;
 mod deserialize_with;
 mod util;
+mod version;
 
 const ATTR: &str = "archive_with";
 
@@ -1,39 +1,439 @@
-use std::iter;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    iter,
+};
 
-use quote::quote;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote};
 use syn::{
+    meta::ParseNestedMeta,
     parenthesized,
     parse::{Parse, ParseStream},
     parse_quote,
-    token::Token as TokenTrait,
-    Attribute, Error, Expr, Field, Ident, LitStr, Path, Result, Token, Type,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::{self, Token as TokenTrait},
+    Attribute, Data, DeriveInput, Error, Expr, ExprBlock, ExprCall, Field, GenericParam, Generics,
+    Ident, Index, LitInt, LitStr, Member, Path, PathArguments, Result, Token, Type, Variant,
+    WherePredicate,
 };
 
-use crate::ATTR;
+use crate::{version::Version, ATTR};
+
+pub struct TopAttributes {
+    pub from: Vec<Type>,
+    /// The rkyv version that the generated code targets.
+    pub version: Version,
+    /// Path to the rkyv crate, imported as `__rkyv` by the generated code.
+    pub crate_path: Path,
+    pub constructor: Option<Path>,
+    pub default: Option<DefaultBase>,
+    /// Functions that create the values of remote fields which are omitted
+    /// in the mirror.
+    pub omitted: Vec<(Ident, Path)>,
+    /// Error type to return when serializing unknown variants of an enum.
+    pub other_error: Option<Type>,
+    /// The `partial` key, if specified, for enums that don't map all
+    /// variants of the remote type.
+    pub partial: Option<Path>,
+    /// Whether the remote type is checked to match the mirror at compile time.
+    pub exhaustive: bool,
+    pub bound: Bounds,
+    /// Conversion of the whole remote type into the mirror.
+    pub into: Option<Path>,
+    pub from_mirror: Option<FromMirror>,
+    pub transparent: Option<Transparent>,
+}
+
+/// Conversion of the whole mirror back into the remote type.
+pub struct FromMirror {
+    pub path: Path,
+    /// Whether the conversion returns a `Result`, alongside its error type
+    /// if specified.
+    pub fallible: Option<Option<Type>>,
+}
+
+/// Base value for all fields of the remote type that are not set otherwise.
+pub struct DefaultBase {
+    /// The `default` key itself, used for error spans.
+    pub key: Path,
+    pub function: Option<Path>,
+}
+
+impl DefaultBase {
+    pub fn make_expr(&self) -> Expr {
+        match self.function {
+            Some(ref function) => parse_quote! { #function() },
+            None => parse_quote! { ::core::default::Default::default() },
+        }
+    }
+}
+
+/// Conversion between the remote type and the single field of a transparent
+/// mirror.
+pub struct Transparent {
+    /// The `transparent` key itself, used for error spans.
+    pub key: Path,
+    /// Whether the field is borrowed through `AsRef` instead of converted
+    /// through `Into`.
+    pub as_ref: bool,
+}
+
+impl Transparent {
+    /// Gets the field of type `ty` out of the remote type `from_ty`.
+    pub fn make_expr(&self, from_ty: &Type, ty: &Type) -> Expr {
+        if self.as_ref {
+            parse_quote! { (*<#from_ty as ::core::convert::AsRef<#ty>>::as_ref(field)) }
+        } else {
+            parse_quote! {
+                ::core::convert::Into::<#ty>::into(<#from_ty as Clone>::clone(field))
+            }
+        }
+    }
+}
+
+/// Collects errors so that all of them are reported at once.
+#[derive(Default)]
+pub struct Errors(Option<Error>);
+
+impl Errors {
+    pub fn push(&mut self, error: Error) {
+        match self.0 {
+            Some(ref mut errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    /// The value of `result` if it's not an error, otherwise the error is
+    /// collected.
+    pub fn ok<T>(&mut self, result: Result<T>) -> Option<T> {
+        result.map_err(|err| self.push(err)).ok()
+    }
+
+    pub fn finish(self) -> Result<()> {
+        self.0.map_or(Ok(()), Err)
+    }
+}
 
-pub fn parse_top_attrs(attrs: &[Attribute]) -> Result<Vec<Type>> {
+/// Parses all top level attributes.
+///
+/// Errors are collected in `errors` and parsing continues with the next
+/// attribute so that as many errors as possible are reported at once.
+pub fn parse_top_attrs(attrs: &[Attribute], errors: &mut Errors) -> TopAttributes {
     let mut from = Vec::new();
+    let mut version = None;
+    let mut crate_path = None;
+    let mut constructor = None;
+    let mut default = None;
+    let mut omitted = Vec::new();
+    let mut other_error = None;
+    let mut partial = None;
+    let mut exhaustive = false;
+    let mut bound = Bounds::default();
+    let mut into = None;
+    let mut from_mirror: Option<Path> = None;
+    let mut try_from_mirror: Option<Path> = None;
+    let mut from_mirror_error = None;
+    let mut transparent = None;
 
     for attr in attrs {
         if !attr.path().is_ident(ATTR) {
             continue;
         }
 
-        attr.parse_nested_meta(|meta| {
+        let res = attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("from") {
                 let content;
                 parenthesized!(content in meta.input);
-                let mut types = Vec::parse_terminated::<Token![,]>(&content)?;
-                from.append(&mut types);
+                let types = Vec::parse_terminated::<Token![,]>(&content)?;
+                from.extend(types.into_iter().map(turbofish));
+
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                let value = meta.value()?;
+
+                // rkyv 0.7 expects a string literal whereas 0.8 expects a path
+                let path = if value.peek(LitStr) {
+                    value.parse::<LitStr>()?.parse()?
+                } else {
+                    value.parse()?
+                };
+
+                crate_path = Some(path);
+
+                Ok(())
+            } else if meta.path.is_ident("rkyv") {
+                version = Some(Version::parse(&meta.value()?.parse()?)?);
+
+                Ok(())
+            } else if meta.path.is_ident("constructor") {
+                constructor = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                let function = if meta.input.peek(Token![=]) {
+                    Some(meta.value()?.parse::<LitStr>()?.parse()?)
+                } else {
+                    None
+                };
+
+                default = Some(DefaultBase {
+                    key: meta.path,
+                    function,
+                });
+
+                Ok(())
+            } else if meta.path.is_ident("omitted") {
+                meta.parse_nested_meta(|meta| {
+                    let name = meta.path.require_ident()?.clone();
+                    let function = meta.value()?.parse::<LitStr>()?.parse()?;
+                    omitted.push((name, function));
+
+                    Ok(())
+                })
+            } else if meta.path.is_ident("other_error") {
+                other_error = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+
+                Ok(())
+            } else if meta.path.is_ident("partial") {
+                partial = Some(meta.path);
+
+                Ok(())
+            } else if meta.path.is_ident("transparent") {
+                let mut as_ref = false;
+
+                if meta.input.peek(token::Paren) {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("as_ref") {
+                            as_ref = true;
+
+                            Ok(())
+                        } else {
+                            Err(meta.error("expected `as_ref`"))
+                        }
+                    })?;
+                }
+
+                transparent = Some(Transparent {
+                    key: meta.path,
+                    as_ref,
+                });
+
+                Ok(())
+            } else if meta.path.is_ident("exhaustive") {
+                exhaustive = true;
+
+                Ok(())
+            } else if meta.path.is_ident("bound") {
+                bound.parse_nested(&meta)
+            } else if meta.path.is_ident("into") {
+                into = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+
+                Ok(())
+            } else if meta.path.is_ident("from_mirror") {
+                from_mirror = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+
+                Ok(())
+            } else if meta.path.is_ident("try_from_mirror") {
+                try_from_mirror = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+
+                Ok(())
+            } else if meta.path.is_ident("from_mirror_error") {
+                from_mirror_error = Some(meta.value()?.parse::<LitStr>()?.parse()?);
 
                 Ok(())
             } else {
-                Err(Error::new_spanned(meta.path, "expected `from`"))
+                let msg = "expected `from`, `crate`, `rkyv`, `constructor`, `default`, `omitted`, \
+                    `other_error`, `partial`, `exhaustive`, `bound`, `into`, `from_mirror`, \
+                    `try_from_mirror`, `from_mirror_error`, or `transparent`";
+
+                Err(Error::new_spanned(meta.path, msg))
             }
-        })?;
+        });
+
+        errors.ok(res);
+    }
+
+    for (i, ty) in from.iter().enumerate() {
+        let tokens = quote!(#ty).to_string();
+
+        if from[..i]
+            .iter()
+            .any(|prev| quote!(#prev).to_string() == tokens)
+        {
+            errors.push(Error::new_spanned(ty, "duplicate type in `from`"));
+        }
+    }
+
+    let from_mirror = match (from_mirror, try_from_mirror) {
+        (Some(path), None) => {
+            if let Some(ref error) = from_mirror_error {
+                let msg = "`from_mirror_error` requires `try_from_mirror` instead of `from_mirror`";
+                errors.push(Error::new_spanned(error, msg));
+            }
+
+            Some(FromMirror {
+                path,
+                fallible: None,
+            })
+        }
+        (None, Some(path)) => Some(FromMirror {
+            path,
+            fallible: Some(from_mirror_error),
+        }),
+        (Some(_), Some(path)) => {
+            let msg = "`try_from_mirror` cannot be combined with `from_mirror`";
+            errors.push(Error::new_spanned(path, msg));
+
+            None
+        }
+        (None, None) => {
+            if let Some(ref error) = from_mirror_error {
+                let msg = "`from_mirror_error` requires `try_from_mirror`";
+                errors.push(Error::new_spanned(error, msg));
+            }
+
+            None
+        }
+    };
+
+    TopAttributes {
+        from,
+        version: version.unwrap_or_default(),
+        crate_path: crate_path.unwrap_or_else(|| parse_quote! { ::rkyv }),
+        constructor,
+        default,
+        omitted,
+        other_error,
+        partial,
+        exhaustive,
+        bound,
+        into,
+        from_mirror,
+        transparent,
+    }
+}
+
+/// Adds `::` in front of all generic arguments of the type so that it can
+/// also be used in expression and pattern position, e.g. `Remote::<A> { .. }`.
+fn turbofish(mut ty: Type) -> Type {
+    if let Type::Path(ref mut ty) = ty {
+        for segment in ty.path.segments.iter_mut() {
+            if let PathArguments::AngleBracketed(ref mut args) = segment.arguments {
+                args.colon2_token.get_or_insert_with(Default::default);
+            }
+        }
+    }
+
+    ty
+}
+
+/// The path of the remote enum type without its generic arguments.
+///
+/// Rust rejects lifetime arguments in front of a variant within patterns and
+/// struct expressions so the arguments are left to inference instead.
+pub fn enum_path(from_ty: &Type) -> TokenStream {
+    match from_ty {
+        Type::Path(ty) if ty.qself.is_none() => {
+            let mut path = ty.path.clone();
+
+            if let Some(segment) = path.segments.last_mut() {
+                segment.arguments = PathArguments::None;
+            }
+
+            quote! { #path }
+        }
+        _ => quote! { #from_ty },
+    }
+}
+
+/// Adds the generic parameter `param` to `generics` right after all
+/// lifetimes because those must be declared first.
+pub fn add_param(generics: &Generics, param: GenericParam) -> Generics {
+    let mut generics = generics.clone();
+    let idx = generics.lifetimes().count();
+    generics.params.insert(idx, param);
+
+    generics
+}
+
+/// The generic parameters of `generics` without bounds and defaults so they
+/// can declare a type alias within a function body.
+pub fn alias_generics(generics: &Generics) -> TokenStream {
+    let params = generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(param) => {
+            let lifetime = &param.lifetime;
+
+            quote! { #lifetime }
+        }
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+
+            quote! { #ident }
+        }
+        GenericParam::Const(param) => {
+            let ident = &param.ident;
+            let ty = &param.ty;
+
+            quote! { const #ident: #ty }
+        }
+    });
+
+    if generics.params.is_empty() {
+        TokenStream::new()
+    } else {
+        quote! { < #( #params ),* > }
     }
+}
+
+/// Where predicates that replace the inferred ones, specified through
+/// `bound(archive = "...", serialize = "...", deserialize = "...")`.
+#[derive(Default)]
+pub struct Bounds {
+    pub archive: Option<Vec<WherePredicate>>,
+    pub serialize: Option<Vec<WherePredicate>>,
+    pub deserialize: Option<Vec<WherePredicate>>,
+}
 
-    Ok(from)
+#[derive(Copy, Clone)]
+pub enum BoundKind {
+    Archive,
+    Serialize,
+    Deserialize,
+}
+
+impl Bounds {
+    fn parse_nested(&mut self, meta: &ParseNestedMeta<'_>) -> Result<()> {
+        meta.parse_nested_meta(|meta| {
+            let bounds = if meta.path.is_ident("archive") {
+                &mut self.archive
+            } else if meta.path.is_ident("serialize") {
+                &mut self.serialize
+            } else if meta.path.is_ident("deserialize") {
+                &mut self.deserialize
+            } else {
+                return Err(meta.error("expected `archive`, `serialize`, or `deserialize`"));
+            };
+
+            let predicates = meta
+                .value()?
+                .parse::<LitStr>()?
+                .parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+
+            *bounds = Some(predicates.into_iter().collect());
+
+            Ok(())
+        })
+    }
+
+    pub fn get(&self, kind: BoundKind) -> Option<&Vec<WherePredicate>> {
+        match kind {
+            BoundKind::Archive => self.archive.as_ref(),
+            BoundKind::Serialize => self.serialize.as_ref(),
+            BoundKind::Deserialize => self.deserialize.as_ref(),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -41,137 +441,1139 @@ pub struct ParsedAttributes {
     pub from: Option<Type>,
     pub via: Option<Vec<Type>>,
     pub getter: Option<Getter>,
+    pub setter: Option<Setter>,
+    /// Name or index of the corresponding field in the remote type.
+    pub rename: Option<Member>,
+    /// Dotted path to the corresponding field nested within the remote type.
+    pub path: Option<Punctuated<Member, Token![.]>>,
+    /// Types of the levels of `path` that contain the field, outermost first,
+    /// so that `DeserializeWith` can create them through struct literals.
+    pub nested: Option<Vec<Type>>,
+    pub bound: Bounds,
+    /// Whether the inferred where predicates are left out e.g. for recursive
+    /// types.
+    pub omit_bounds: bool,
+    /// Argument position of the field for the top level `constructor`.
+    pub arg: Option<LitInt>,
+    /// Converts the remote type into the field's type.
+    pub serialize_fn: Option<Path>,
+    /// Converts the field's type back into the remote type.
+    pub deserialize_fn: Option<Path>,
+    /// Type that the remote type is converted into through `Into` and back
+    /// through `From`.
+    pub via_into: Option<Type>,
+    /// Same as `via_into` but converted back through `TryFrom`.
+    pub via_try_from: Option<Type>,
 }
 
 pub struct Getter {
-    pub path: Path,
+    /// Path to a function or any other callable expression, e.g. a closure.
+    pub expr: Expr,
     pub owned_self: bool,
+    /// Whether the getter returns a `Result` or `Option`, alongside the error
+    /// type if specified.
+    pub fallible: Option<Option<Type>>,
+    /// Whether the getter returns a reference that dereferences to the
+    /// field's type, or to the given type if specified.
+    pub borrow: Option<Option<Type>>,
 }
 
 impl Getter {
     pub fn make_expr(&self, from_ty: &Type) -> Expr {
+        let Self {
+            expr, owned_self, ..
+        } = self;
+
+        let (arg, generics, param): (Expr, TokenStream, Type) = if *owned_self {
+            // Copies the remote type if it is `Copy`, see `GetterValues`
+            (
+                parse_quote! { (&GetterSelf::<#from_ty>(field)).to_owned_self() },
+                quote! { <U, R> },
+                parse_quote! { U },
+            )
+        } else {
+            (
+                parse_quote! { field },
+                quote! { <'a, U: ?Sized, R> },
+                parse_quote! { &'a U },
+            )
+        };
+
+        // The call is spanned to the getter so that type errors of its value
+        // point at the getter
+        let span = expr.span();
+
+        if let Expr::Path(path) = expr {
+            let mut call: ExprCall = parse_quote! { #path (#arg) };
+            call.paren_token = token::Paren(span);
+
+            return Expr::Call(call);
+        }
+
+        // Closures need their parameter's type to be known upfront
+        let mut block: ExprBlock = parse_quote! {{
+            fn getter #generics (
+                getter: impl ::core::ops::FnOnce(#param) -> R,
+                remote: #param,
+            ) -> R {
+                getter(remote)
+            }
+
+            getter::<#from_ty, _>(#expr, #arg)
+        }};
+        block.block.brace_token = token::Brace(span);
+
+        Expr::Block(block)
+    }
+}
+
+pub struct Setter {
+    pub path: Path,
+    pub owned_self: bool,
+}
+
+impl Setter {
+    /// Applies `value` onto `remote`.
+    pub fn make_expr(&self, remote: &Ident, value: &Expr) -> Expr {
         let Self { path, owned_self } = self;
 
         if *owned_self {
-            parse_quote! { #path (<#from_ty as Clone>::clone(field)) }
+            parse_quote! { #remote = #path (#remote, #value) }
         } else {
-            parse_quote! { #path (field) }
+            parse_quote! { #path (&mut #remote, #value) }
         }
     }
 }
 
 impl ParsedAttributes {
-    pub fn new(attrs: &[Attribute]) -> Result<Self> {
+    pub fn new(attrs: &[Attribute], version: Version) -> Result<Self> {
         let mut parsed = ParsedAttributes::default();
-        let mut getter_path = None;
-        let mut getter_owned = false;
+        let mut errors = Errors::default();
+        let mut getter_expr = None;
+        let mut getter_owned = None;
+        let mut getter_fallible = None;
+        let mut getter_borrow = None;
+        let mut setter_path = None;
+        let mut setter_owned = None;
 
         for attr in attrs {
             if attr.path().is_ident(ATTR) {
-                attr.parse_nested_meta(|meta| {
+                let res = attr.parse_nested_meta(|meta| {
                     if meta.path.is_ident("from") {
+                        if parsed.from.is_some() {
+                            return Err(meta.error("duplicate `from`"));
+                        }
+
                         let content;
                         parenthesized!(content in meta.input);
                         parsed.from = Some(content.parse()?);
                     } else if meta.path.is_ident("via") {
                         let content;
                         parenthesized!(content in meta.input);
-                        parsed.via = Some(Vec::parse_separated_nonempty::<Token![,]>(&content)?);
+                        let via = Vec::parse_separated_nonempty::<Token![,]>(&content)?;
+
+                        if version.is_v0_8() && via.len() > 1 {
+                            let msg = "rkyv 0.8 does not support nested wrappers, \
+                                combine them into a single wrapper instead";
+
+                            return Err(Error::new_spanned(&via[1], msg));
+                        }
+
+                        parsed.via = Some(via);
                     } else if meta.path.is_ident("getter") {
-                        getter_path = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                        let value = meta.value()?;
+
+                        getter_expr = Some(if value.peek(LitStr) {
+                            value.parse::<LitStr>()?.parse()?
+                        } else {
+                            value.parse()?
+                        });
                     } else if meta.path.is_ident("getter_owned") {
-                        getter_owned = true;
+                        getter_owned = Some(meta.path);
+                    } else if meta.path.is_ident("fallible") {
+                        let error_ty = if meta.input.peek(Token![=]) {
+                            Some(meta.value()?.parse::<LitStr>()?.parse()?)
+                        } else {
+                            None
+                        };
+
+                        getter_fallible = Some((meta.path, error_ty));
+                    } else if meta.path.is_ident("borrow") {
+                        let borrowed = if meta.input.peek(token::Paren) {
+                            let content;
+                            parenthesized!(content in meta.input);
+                            let ty: Type = content.parse()?;
+
+                            match ty {
+                                Type::Slice(_) => {}
+                                Type::Path(ref path) if path.qself.is_none() && path.path.is_ident("str") => {}
+                                _ => {
+                                    let msg = "expected `str` or a slice such as `[T]`";
+
+                                    return Err(Error::new_spanned(ty, msg));
+                                }
+                            }
+
+                            Some(ty)
+                        } else {
+                            None
+                        };
+
+                        getter_borrow = Some((meta.path, borrowed));
+                    } else if meta.path.is_ident("setter") {
+                        setter_path = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if meta.path.is_ident("setter_owned") {
+                        setter_owned = Some(meta.path);
+                    } else if meta.path.is_ident("rename") {
+                        parsed.rename = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if meta.path.is_ident("path") {
+                        parsed.path = Some(parse_field_path(&meta.value()?.parse()?)?);
+                    } else if meta.path.is_ident("nested") {
+                        let content;
+                        parenthesized!(content in meta.input);
+                        let types = Vec::parse_separated_nonempty::<Token![,]>(&content)?;
+                        parsed.nested = Some(types.into_iter().map(turbofish).collect());
+                    } else if meta.path.is_ident("bound") {
+                        parsed.bound.parse_nested(&meta)?;
+                    } else if meta.path.is_ident("omit_bounds") {
+                        parsed.omit_bounds = true;
+                    } else if meta.path.is_ident("arg") {
+                        parsed.arg = Some(meta.value()?.parse()?);
+                    } else if meta.path.is_ident("serialize_fn") {
+                        parsed.serialize_fn = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if meta.path.is_ident("deserialize_fn") {
+                        parsed.deserialize_fn = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if meta.path.is_ident("via_into") {
+                        let content;
+                        parenthesized!(content in meta.input);
+                        parsed.via_into = Some(content.parse()?);
+                    } else if meta.path.is_ident("via_try_from") {
+                        let content;
+                        parenthesized!(content in meta.input);
+                        parsed.via_try_from = Some(content.parse()?);
                     } else {
-                        let msg = "expected `from`, `via`, `getter`, or `getter_owned`";
+                        let msg = "expected `from`, `via`, `getter`, `getter_owned`, `fallible`, \
+                            `borrow`, `setter`, `setter_owned`, `rename`, `path`, `nested`, `bound`, `omit_bounds`, `arg`, \
+                            `serialize_fn`, `deserialize_fn`, `via_into`, or `via_try_from`";
 
                         return Err(meta.error(msg));
                     }
 
                     Ok(())
-                })?;
+                });
+
+                errors.ok(res);
             }
         }
 
-        if let Some(path) = getter_path {
-            parsed.getter = Some(Getter {
-                path,
-                owned_self: getter_owned,
-            });
+        match (getter_expr, getter_owned, getter_fallible, getter_borrow) {
+            (Some(expr), owned, fallible, borrow) => {
+                if let Some((ref key, None)) = fallible {
+                    if !version.is_v0_8() {
+                        let msg = "rkyv 0.7 requires the error type through `fallible = \"...\"`";
+                        errors.push(Error::new_spanned(key, msg));
+                    }
+                }
+
+                if let (Some(_), Some((ref key, _))) = (&fallible, &borrow) {
+                    let msg = "`borrow` cannot be combined with `fallible`";
+                    errors.push(Error::new_spanned(key, msg));
+                }
+
+                parsed.getter = Some(Getter {
+                    expr,
+                    owned_self: owned.is_some(),
+                    fallible: fallible.map(|(_, error_ty)| error_ty),
+                    borrow: borrow.map(|(_, borrowed)| borrowed),
+                })
+            }
+            (None, owned, fallible, borrow) => {
+                if let Some(owned) = owned {
+                    let msg = "`getter_owned` requires `getter = \"...\"`";
+                    errors.push(Error::new_spanned(owned, msg));
+                }
+
+                if let Some((key, _)) = fallible {
+                    let msg = "`fallible` requires `getter = \"...\"`";
+                    errors.push(Error::new_spanned(key, msg));
+                }
+
+                if let Some((key, _)) = borrow {
+                    let msg = "`borrow` requires `getter = \"...\"`";
+                    errors.push(Error::new_spanned(key, msg));
+                }
+            }
+        }
+
+        match (setter_path, setter_owned) {
+            (Some(path), owned) => {
+                parsed.setter = Some(Setter {
+                    path,
+                    owned_self: owned.is_some(),
+                })
+            }
+            (None, Some(owned)) => {
+                let msg = "`setter_owned` requires `setter = \"...\"`";
+                errors.push(Error::new_spanned(owned, msg));
+            }
+            (None, None) => {}
+        }
+
+        if let (None, Some(deserialize_fn)) = (&parsed.serialize_fn, &parsed.deserialize_fn) {
+            let msg = "`deserialize_fn` requires `serialize_fn = \"...\"`";
+            errors.push(Error::new_spanned(deserialize_fn, msg));
+        }
+
+        let conversions = [
+            (
+                "serialize_fn",
+                parsed.serialize_fn.as_ref().map(|path| quote!(#path)),
+            ),
+            ("via_into", parsed.via_into.as_ref().map(|ty| quote!(#ty))),
+            (
+                "via_try_from",
+                parsed.via_try_from.as_ref().map(|ty| quote!(#ty)),
+            ),
+        ];
+
+        let mut conversions = conversions
+            .into_iter()
+            .filter_map(|(key, tokens)| Some((key, tokens?)));
+
+        if let Some((key, tokens)) = conversions.next() {
+            if parsed.from.is_none() {
+                let msg = format!("`{key}` requires `from(...)`");
+                errors.push(Error::new_spanned(&tokens, msg));
+            }
+
+            if parsed.via.is_some() {
+                let msg = format!("`{key}` cannot be combined with `via`");
+                errors.push(Error::new_spanned(&tokens, msg));
+            }
+
+            for (other, tokens) in conversions {
+                let msg = format!("`{other}` cannot be combined with `{key}`");
+                errors.push(Error::new_spanned(tokens, msg));
+            }
+        }
+
+        if let Some(ref path) = parsed.path {
+            let conflicts = [
+                ("getter", parsed.getter.is_some()),
+                ("setter", parsed.setter.is_some()),
+                ("rename", parsed.rename.is_some()),
+                ("arg", parsed.arg.is_some()),
+            ];
+
+            for (key, _) in conflicts.into_iter().filter(|(_, conflict)| *conflict) {
+                let msg = format!("`path` cannot be combined with `{key}`");
+                errors.push(Error::new_spanned(path, msg));
+            }
         }
 
+        match (&parsed.path, &parsed.nested) {
+            (Some(path), Some(nested)) if nested.len() + 1 != path.len() => {
+                let msg = format!(
+                    "`nested(...)` requires a type for each of the {} levels of `path` that \
+                    contain the field",
+                    path.len() - 1
+                );
+                errors.push(Error::new_spanned(&nested[0], msg));
+            }
+            (None, Some(nested)) => {
+                let msg = "`nested(...)` requires `path = \"...\"`";
+                errors.push(Error::new_spanned(&nested[0], msg));
+            }
+            _ => {}
+        }
+
+        errors.finish()?;
+
         Ok(parsed)
     }
+
+    /// The member of the remote type that corresponds to the field `member`.
+    pub fn remote_member(&self, member: Member) -> Member {
+        self.rename.clone().unwrap_or(member)
+    }
+
+    /// The type that the remote value is converted into through
+    /// `serialize_fn`, `via_into`, or `via_try_from`, alongside the
+    /// conversion of the reference `expr` to the remote value.
+    ///
+    /// `via_into` and `via_try_from` convert the reference if possible and
+    /// clone the remote value otherwise, see [`via_value_defs`].
+    pub fn conversion(&self, field: &Field, expr: Expr) -> Option<(Type, Expr)> {
+        let from_ty = self.from.as_ref()?;
+
+        match *self {
+            Self {
+                serialize_fn: Some(ref serialize_fn),
+                ..
+            } => Some((field.ty.clone(), parse_quote! { #serialize_fn(#expr) })),
+            Self {
+                via_into: Some(ref ty),
+                ..
+            }
+            | Self {
+                via_try_from: Some(ref ty),
+                ..
+            } => Some((
+                ty.clone(),
+                parse_quote! {
+                    (&ViaValue::<#from_ty, #ty>(#expr, ::core::marker::PhantomData)).into_via()
+                },
+            )),
+            _ => None,
+        }
+    }
 }
 
-pub fn with<B, F: FnMut(B, &Type) -> B>(field: &Field, init: B, f: F) -> Result<B> {
-    let fields = field
+/// Parses the fields of a dotted path such as `inner.x` or `inner.0`.
+fn parse_field_path(lit: &LitStr) -> Result<Punctuated<Member, Token![.]>> {
+    lit.value()
+        .split('.')
+        .map(|segment| {
+            let member = match syn::parse_str(segment) {
+                Ok(Member::Named(mut ident)) => {
+                    ident.set_span(lit.span());
+
+                    Member::Named(ident)
+                }
+                Ok(Member::Unnamed(index)) => Member::Unnamed(Index {
+                    index: index.index,
+                    span: lit.span(),
+                }),
+                Err(_) => {
+                    let msg = "expected fields separated by dots such as `inner.x`";
+
+                    return Err(Error::new_spanned(lit, msg));
+                }
+            };
+
+            Ok(member)
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct VariantAttributes {
+    /// Name of the corresponding variant in the remote type.
+    pub rename: Option<Ident>,
+    /// Further variants of the remote type that are serialized as this variant.
+    pub aliases: Vec<Ident>,
+    /// Whether unknown variants of the remote type are serialized as this
+    /// variant.
+    pub other: bool,
+}
+
+impl VariantAttributes {
+    pub fn new(attrs: &[Attribute]) -> Result<Self> {
+        let mut parsed = VariantAttributes::default();
+        let mut errors = Errors::default();
+
+        for attr in attrs {
+            if attr.path().is_ident(ATTR) {
+                let res = attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        parsed.rename = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if meta.path.is_ident("alias") {
+                        parsed
+                            .aliases
+                            .push(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if meta.path.is_ident("other") {
+                        parsed.other = true;
+                    } else {
+                        return Err(meta.error("expected `rename`, `alias`, or `other`"));
+                    }
+
+                    Ok(())
+                });
+
+                errors.ok(res);
+            }
+        }
+
+        errors.finish()?;
+
+        Ok(parsed)
+    }
+
+    /// The variant of the remote type that the variant `variant` is
+    /// deserialized into.
+    pub fn remote_variant<'a>(&'a self, variant: &'a Ident) -> &'a Ident {
+        self.rename.as_ref().unwrap_or(variant)
+    }
+
+    /// All variants of the remote type that are serialized as the variant
+    /// `variant`.
+    ///
+    /// The `other` variant only names the remote variants that it is mapped
+    /// onto through `rename` or `alias` since it doesn't need a counterpart in
+    /// the remote type, all other remote variants fall back to it anyway.
+    pub fn remote_variants<'a>(&'a self, variant: &'a Ident) -> Vec<&'a Ident> {
+        let remote = match self.rename {
+            Some(ref rename) => Some(rename),
+            None if self.other => None,
+            None => Some(variant),
+        };
+
+        remote.into_iter().chain(&self.aliases).collect()
+    }
+}
+
+/// Checks that a transparent mirror is a struct with exactly one field.
+pub fn validate_transparent(data: &Data, transparent: &Transparent, errors: &mut Errors) {
+    let single_field = match data {
+        Data::Struct(data) => data.fields.len() == 1,
+        Data::Enum(_) | Data::Union(_) => false,
+    };
+
+    if !single_field {
+        let msg = "`transparent` requires a struct with exactly one field";
+        errors.push(Error::new_spanned(&transparent.key, msg));
+    }
+}
+
+/// Parsed attributes of the fields and enum variants, see [`validate_attrs`].
+#[derive(Default)]
+pub struct DataAttributes<'a> {
+    /// Fields of the struct or of all enum variants.
+    pub fields: Vec<(&'a Field, ParsedAttributes)>,
+    pub variants: Vec<(&'a Variant, VariantAttributes)>,
+}
+
+/// Parses and checks the attributes of all fields and enum variants.
+///
+/// Fields and variants with invalid attributes are left out of the result
+/// since their errors are collected already.
+pub fn validate_attrs<'a>(
+    data: &'a Data,
+    version: Version,
+    errors: &mut Errors,
+) -> DataAttributes<'a> {
+    let mut parsed = DataAttributes::default();
+
+    let fields: Vec<_> = match data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => {
+            for variant in data.variants.iter() {
+                if let Some(attrs) = errors.ok(VariantAttributes::new(&variant.attrs)) {
+                    parsed.variants.push((variant, attrs));
+                }
+            }
+
+            data.variants.iter().flat_map(|v| v.fields.iter()).collect()
+        }
+        Data::Union(_) => return parsed,
+    };
+
+    for field in fields {
+        let Some(attrs) = errors.ok(ParsedAttributes::new(&field.attrs, version)) else {
+            continue;
+        };
+
+        if let (Data::Enum(_), Some(getter)) = (data, &attrs.getter) {
+            let msg = "`getter` is not supported for enum variants";
+            errors.push(Error::new_spanned(&getter.expr, msg));
+        }
+
+        if let (Data::Enum(_), Some(path)) = (data, &attrs.path) {
+            let msg = "`path` is not supported for enum variants";
+            errors.push(Error::new_spanned(path, msg));
+        }
+
+        let Some(with_tys) = errors.ok(with_attrs(field, version)) else {
+            continue;
+        };
+
+        // rkyv's own wrappers used to replace `via` instead of being applied
+        // on top of it so they were commonly repeated within `via`
+        if let Some(ref via_tys) = attrs.via {
+            let tokens = |tys: &[Type]| tys.iter().map(|ty| quote!(#ty).to_string()).collect();
+            let with_tys: Vec<String> = tokens(&with_tys);
+
+            if !with_tys.is_empty() && tokens(via_tys).starts_with(&with_tys) {
+                let msg = "`via(...)` repeats the wrappers of rkyv's field attribute which are \
+                    already applied on top of `via(...)`; remove them from `via(...)`";
+                errors.push(Error::new_spanned(&via_tys[0], msg));
+            }
+        }
+
+        parsed.fields.push((field, attrs));
+    }
+
+    parsed
+}
+
+/// Wrapper types of rkyv's own field attribute, outermost first.
+fn with_attrs(field: &Field, version: Version) -> Result<Vec<Type>> {
+    let wrappers = field
         .attrs
         .iter()
-        .filter_map(|attr| {
-            if attr.path().is_ident("with") {
-                Some(attr.parse_args_with(Vec::parse_separated_nonempty::<Token![,]>))
-            } else {
-                None
-            }
-        })
+        .filter_map(|attr| version.with_wrappers(attr))
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(fields.iter().flatten().rev().fold(init, f))
+    Ok(wrappers.into_iter().flatten().collect())
 }
 
-pub fn with_ty(field: &Field) -> Result<(Type, ParsedAttributes)> {
+/// The type that a field is archived from, alongside all wrappers that are
+/// applied to it, outermost first.
+///
+/// The remote type is wrapped by `via`, or by the field's type in case only
+/// `from` is specified, and then by rkyv's own wrappers of the field.
+fn wrappers(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    version: Version,
+) -> Result<(Type, Vec<Type>)> {
     let ty = &field.ty;
-    let parsed_attrs = ParsedAttributes::new(&field.attrs)?;
-
-    let ty = match (&parsed_attrs.from, &parsed_attrs.via) {
-        (Some(from_ty), Some(via_tys)) => via_tys.iter().rev().fold(
-            from_ty.clone(),
-            |ty, wrapper| parse_quote! { ::rkyv::with::With<#ty, #wrapper> },
-        ),
-        (Some(from_ty), None) => parse_quote! { ::rkyv::with::With<#from_ty, #ty> },
-        (None, Some(via_tys)) => via_tys.iter().rev().fold(
-            ty.clone(),
-            |ty, wrapper| parse_quote! { ::rkyv::with::With<#ty, #wrapper> },
-        ),
-        (None, None) => with(
-            field,
-            ty.clone(),
-            |ty, wrapper| parse_quote! { ::rkyv::with::With<#ty, #wrapper> },
-        )?,
+    let mut wrappers = with_attrs(field, version)?;
+
+    // The generated wrapper of `serialize_fn` takes the place of `via`
+    let fn_wrapper = fn_wrapper(field, attrs).map(|wrapper| vec![parse_quote!(#wrapper)]);
+    let via_tys = attrs.via.as_ref().or(fn_wrapper.as_ref());
+
+    let from_ty = match (&attrs.from, via_tys) {
+        (Some(from_ty), Some(via_tys)) => {
+            wrappers.extend(via_tys.iter().cloned());
+
+            from_ty.clone()
+        }
+        (Some(from_ty), None) => {
+            wrappers.push(ty.clone());
+
+            from_ty.clone()
+        }
+        (None, Some(via_tys)) => {
+            wrappers.extend(via_tys.iter().cloned());
+
+            ty.clone()
+        }
+        (None, None) => ty.clone(),
     };
 
-    Ok((ty, parsed_attrs))
+    Ok((from_ty, wrappers))
 }
 
-pub fn with_cast(field: &Field, expr: Expr) -> Result<Expr> {
+/// The type that `ArchiveWith` serializes a field from, alongside all
+/// wrappers that are applied to it, outermost first.
+///
+/// Fields with `serialize_fn`, `via_into`, or `via_try_from` are serialized
+/// from their converted value which is only wrapped by rkyv's own wrappers of
+/// the field, same as the mirror's field.
+fn serialized_wrappers(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    version: Version,
+) -> Result<(Type, Vec<Type>)> {
+    match attrs.conversion(field, parse_quote!(field)) {
+        Some((ty, _)) => Ok((ty, with_attrs(field, version)?)),
+        None => wrappers(field, attrs, version),
+    }
+}
+
+/// Name of the wrapper type that is generated for a field with
+/// `serialize_fn`, `via_into`, or `via_try_from`.
+///
+/// The name is derived from the field so that every scope of the generated
+/// code can define the wrapper itself, see [`fn_wrapper_defs`].
+fn fn_wrapper(field: &Field, attrs: &ParsedAttributes) -> Option<Ident> {
+    let ParsedAttributes {
+        ref from,
+        ref serialize_fn,
+        ref deserialize_fn,
+        ref via_into,
+        ref via_try_from,
+        ..
+    } = *attrs;
+
+    if serialize_fn.is_none() && via_into.is_none() && via_try_from.is_none() {
+        return None;
+    }
+
     let ty = &field.ty;
-    let parsed_attr = ParsedAttributes::new(&field.attrs)?;
-
-    let expr = match (parsed_attr.from, parsed_attr.via) {
-        (Some(_), None) => parse_quote! { ::rkyv::with::With::<_, #ty>::cast(#expr) },
-        (_, Some(via_tys)) => via_tys.iter().rev().fold(
-            expr,
-            |expr, wrapper| parse_quote! { ::rkyv::with::With::<_, #wrapper>::cast(#expr) },
-        ),
-        (None, None) => with(
-            field,
-            expr,
-            |expr, wrapper| parse_quote! { ::rkyv::with::With::<_, #wrapper>::cast(#expr) },
-        )?,
-    };
+    let mut hasher = DefaultHasher::new();
+    quote!(#from #ty #serialize_fn #deserialize_fn #via_into #via_try_from)
+        .to_string()
+        .hash(&mut hasher);
+
+    Some(format_ident!("__ArchiveWithFn{:016x}", hasher.finish()))
+}
+
+/// Defines the wrapper types of all fields with `serialize_fn`, `via_into`,
+/// or `via_try_from` which convert the remote type and then archive the
+/// converted value.
+///
+/// `DeserializeWith` is only implemented if `with_deserialize` is set because
+/// the conversion back into the remote type might not exist otherwise.
+pub fn fn_wrapper_defs(
+    input: &DeriveInput,
+    data_attrs: &DataAttributes<'_>,
+    with_deserialize: bool,
+    version: Version,
+) -> TokenStream {
+    let fallible = version.fallible();
+    let mut defined = HashSet::new();
+    let mut defs = TokenStream::new();
+    let mut via_value = false;
+
+    for &(field, ref attrs) in data_attrs.fields.iter() {
+        let (Some(wrapper), Some(from_ty)) = (fn_wrapper(field, attrs), &attrs.from) else {
+            continue;
+        };
+
+        // Fields with the same attributes share their wrapper
+        if !defined.insert(wrapper.clone()) {
+            continue;
+        }
+
+        let mut deserialize_bounds = Vec::new();
+
+        // The type that is archived and the conversion of `field` into it
+        let Some((ty, serialize)) = attrs.conversion(field, parse_quote!(field)) else {
+            continue;
+        };
+
+        via_value |= attrs.serialize_fn.is_none();
+
+        // The conversion of `value` back into the remote type
+        let deserialize = match *attrs {
+            ParsedAttributes {
+                serialize_fn: Some(_),
+                ref deserialize_fn,
+                ..
+            } => deserialize_fn.as_ref().map(|f| quote! { Ok(#f(value)) }),
+            ParsedAttributes {
+                via_try_from: Some(_),
+                ..
+            } => {
+                let error_ty = parse_quote! { <#from_ty as ::core::convert::TryFrom<#ty>>::Error };
+                let (error, bounds) =
+                    version.error_into(&parse_quote!(__D), parse_quote!(err), Some(&error_ty));
+                deserialize_bounds = bounds;
+
+                Some(quote! {
+                    <#from_ty as ::core::convert::TryFrom<#ty>>::try_from(value)
+                        .map_err(|err| #error)
+                })
+            }
+            _ => Some(quote! { Ok(<#from_ty as ::core::convert::From<#ty>>::from(value)) }),
+        };
+
+        let generics = used_generics(&input.generics, quote!(#from_ty #ty));
+        let (impl_generics, _, _) = generics.split_for_impl();
+        let serialize_generics = add_param(&generics, parse_quote! { __S: #fallible + ?Sized });
+        let (serialize_impl_generics, _, _) = serialize_generics.split_for_impl();
+
+        // The converted value is stored in the resolver so that the conversion
+        // only happens once. `ArchiveWith` itself stores the value in its own
+        // resolver instead, see `GetterValues`.
+        let resolve = version.resolve(&parse_quote!(&value), &parse_quote!(resolver));
+        let resolve_with = version.resolve_with(
+            from_ty,
+            quote! {
+                let (value, resolver) = resolver;
+                #resolve
+            },
+        );
+
+        defs.extend(quote! {
+            #[allow(dead_code)]
+            struct #wrapper;
+
+            impl #impl_generics __rkyv::with::ArchiveWith<#from_ty> for #wrapper
+            where
+                #ty: __rkyv::Archive,
+            {
+                type Archived = <#ty as __rkyv::Archive>::Archived;
+                type Resolver = (#ty, <#ty as __rkyv::Archive>::Resolver);
+
+                #resolve_with
+            }
+
+            impl #serialize_impl_generics __rkyv::with::SerializeWith<#from_ty, __S> for #wrapper
+            where
+                #ty: __rkyv::Serialize<__S>,
+            {
+                #[inline]
+                fn serialize_with(
+                    field: &#from_ty,
+                    serializer: &mut __S,
+                ) -> ::core::result::Result<Self::Resolver, <__S as #fallible>::Error> {
+                    let value: #ty = #serialize;
+                    let resolver = __rkyv::Serialize::<__S>::serialize(&value, serializer)?;
+
+                    ::core::result::Result::Ok((value, resolver))
+                }
+            }
+        });
+
+        let (true, Some(deserialize)) = (with_deserialize, deserialize) else {
+            continue;
+        };
+
+        let deserialize_generics = add_param(&generics, parse_quote! { __D: #fallible + ?Sized });
+        let (deserialize_impl_generics, _, _) = deserialize_generics.split_for_impl();
+
+        defs.extend(quote! {
+            impl #deserialize_impl_generics
+            __rkyv::with::DeserializeWith<<#ty as __rkyv::Archive>::Archived, #from_ty, __D>
+            for #wrapper
+            where
+                #ty: __rkyv::Archive,
+                <#ty as __rkyv::Archive>::Archived: __rkyv::Deserialize<#ty, __D>,
+                #( #deserialize_bounds, )*
+            {
+                #[inline]
+                fn deserialize_with(
+                    field: &<#ty as __rkyv::Archive>::Archived,
+                    deserializer: &mut __D,
+                ) -> ::core::result::Result<#from_ty, <__D as #fallible>::Error> {
+                    let value: #ty = __rkyv::Deserialize::<#ty, __D>::deserialize(field, deserializer)?;
+
+                    #deserialize
+                }
+            }
+        });
+    }
+
+    if via_value {
+        defs.extend(via_value_defs());
+    }
+
+    defs
+}
+
+/// Defines `ViaValue` which converts the remote type for `via_into` and
+/// `via_try_from` through `From<&U>` if it is implemented and clones it
+/// otherwise.
+pub fn via_value_defs() -> TokenStream {
+    quote! {
+        struct ViaValue<'a, U, T>(&'a U, ::core::marker::PhantomData<T>);
+
+        trait IntoFromRef<T> {
+            fn into_via(&self) -> T;
+        }
+
+        impl<'a, U, T> IntoFromRef<T> for ViaValue<'a, U, T>
+        where
+            &'a U: ::core::convert::Into<T>,
+        {
+            fn into_via(&self) -> T {
+                ::core::convert::Into::into(self.0)
+            }
+        }
+
+        trait IntoFromClone<T> {
+            fn into_via(&self) -> T;
+        }
+
+        impl<U: Clone + ::core::convert::Into<T>, T> IntoFromClone<T> for &ViaValue<'_, U, T> {
+            fn into_via(&self) -> T {
+                ::core::convert::Into::into(U::clone(self.0))
+            }
+        }
+    }
+}
+
+/// The parameters of `generics` that occur within `tokens`, without the
+/// where clause.
+///
+/// Parameters that don't occur within an impl's trait or self type are
+/// rejected by the compiler.
+fn used_generics(generics: &Generics, tokens: TokenStream) -> Generics {
+    fn collect_idents(tokens: TokenStream, idents: &mut HashSet<Ident>) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => {
+                    idents.insert(ident);
+                }
+                TokenTree::Group(group) => collect_idents(group.stream(), idents),
+                TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+            }
+        }
+    }
+
+    let mut idents = HashSet::new();
+    collect_idents(tokens, &mut idents);
+
+    let params = generics
+        .params
+        .iter()
+        .filter(|param| {
+            let ident = match param {
+                GenericParam::Lifetime(param) => &param.lifetime.ident,
+                GenericParam::Type(param) => &param.ident,
+                GenericParam::Const(param) => &param.ident,
+            };
+
+            idents.contains(ident)
+        })
+        .cloned()
+        .collect();
+
+    Generics {
+        params,
+        where_clause: None,
+        ..generics.clone()
+    }
+}
+
+/// The type that a field is archived from, alongside the only wrapper that
+/// is applied to it, if any.
+///
+/// rkyv 0.8 deserializes the archived field through the outermost wrapper
+/// directly so nested wrappers cannot be unwrapped.
+fn deserialize_wrapper(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    version: Version,
+) -> Result<(Type, Option<Type>)> {
+    let (from_ty, mut wrappers) = wrappers(field, attrs, version)?;
+
+    if wrappers.len() > 1 {
+        let msg = "rkyv 0.8 cannot deserialize through nested wrappers, \
+            i.e. `via(...)` or `from(...)` alongside `#[rkyv(with = ...)]`";
+
+        return Err(Error::new_spanned(field, msg));
+    }
+
+    Ok((from_ty, wrappers.pop()))
+}
+
+fn wrapped(ty: Type, wrappers: &[Type]) -> Type {
+    wrappers.iter().rev().fold(
+        ty,
+        |ty, wrapper| parse_quote! { __rkyv::with::With<#ty, #wrapper> },
+    )
+}
+
+pub fn with_ty(field: &Field, attrs: &ParsedAttributes, version: Version) -> Result<Type> {
+    let (ty, wrappers) = wrappers(field, attrs, version)?;
+
+    Ok(wrapped(ty, &wrappers))
+}
+
+pub fn with_cast(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    expr: Expr,
+    version: Version,
+) -> Result<Expr> {
+    let (_, wrappers) = serialized_wrappers(field, attrs, version)?;
+
+    let expr = wrappers.iter().rev().fold(
+        expr,
+        |expr, wrapper| parse_quote! { __rkyv::with::With::<_, #wrapper>::cast(#expr) },
+    );
 
     Ok(expr)
 }
 
-pub fn with_inner(field: &Field, attrs: &ParsedAttributes, expr: Expr) -> Result<Expr> {
-    if attrs.from.is_none() && attrs.via.is_none() {
-        with(field, expr, |expr, _| parse_quote! { #expr.into_inner() })
-    } else {
-        let into_inner_count = attrs.via.as_ref().map_or(1, Vec::len);
-        let into_inners = iter::repeat(quote!(.into_inner())).take(into_inner_count);
+pub fn with_inner(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    expr: Expr,
+    version: Version,
+) -> Result<Expr> {
+    let (_, wrappers) = wrappers(field, attrs, version)?;
+    // `iter::repeat_n` would require Rust 1.82
+    #[allow(clippy::manual_repeat_n)]
+    let into_inners = iter::repeat(quote!(.into_inner())).take(wrappers.len());
+
+    Ok(parse_quote! { #expr #( #into_inners )* })
+}
+
+/// Where predicates of the `kind` impl for `fields`.
+///
+/// The top level `bound` replaces the inferred predicates of all fields
+/// whereas a field's `bound` only replaces its own. Fields with
+/// `omit_bounds` and without `bound` contribute no predicates, neither do
+/// borrowed fields when deserializing.
+pub fn field_bounds<'a>(
+    kind: BoundKind,
+    top_bound: &Bounds,
+    fields: impl IntoIterator<Item = &'a (&'a Field, ParsedAttributes)>,
+    version: Version,
+) -> Result<Vec<WherePredicate>> {
+    if let Some(bounds) = top_bound.get(kind) {
+        return Ok(bounds.clone());
+    }
+
+    let mut predicates = Vec::new();
+
+    for &(field, ref attrs) in fields {
+        if let Some(bounds) = attrs.bound.get(kind) {
+            predicates.extend(bounds.iter().cloned());
+
+            continue;
+        } else if attrs.omit_bounds {
+            continue;
+        }
+
+        match kind {
+            BoundKind::Archive => predicates.push(archive_bound(field, attrs, version)?),
+            BoundKind::Serialize => predicates.push(serialize_bound(field, attrs, version)?),
+            BoundKind::Deserialize if is_borrowed(field, attrs)? => {}
+            BoundKind::Deserialize => predicates.extend(deserialize_bounds(field, attrs, version)?),
+        }
+    }
+
+    Ok(predicates)
+}
+
+/// Bound required to archive a field.
+///
+/// For rkyv 0.8 the bound is phrased through the outermost wrapper the same
+/// way rkyv's own derive does it so that both bounds agree with each other.
+fn archive_bound(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    version: Version,
+) -> Result<WherePredicate> {
+    let (from_ty, wrappers) = serialized_wrappers(field, attrs, version)?;
+
+    if !version.is_v0_8() {
+        let ty = wrapped(from_ty, &wrappers);
 
-        Ok(parse_quote! { #expr #( #into_inners )* })
+        return Ok(parse_quote! { #ty: Archive });
     }
+
+    let bound = match wrappers.split_first() {
+        Some((outer, inner)) => {
+            let inner_ty = wrapped(from_ty, inner);
+
+            parse_quote! { #outer: __rkyv::with::ArchiveWith<#inner_ty> }
+        }
+        None => parse_quote! { #from_ty: Archive },
+    };
+
+    Ok(bound)
+}
+
+/// Bound required to serialize a field, see [`archive_bound`].
+fn serialize_bound(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    version: Version,
+) -> Result<WherePredicate> {
+    let (from_ty, wrappers) = serialized_wrappers(field, attrs, version)?;
+
+    if !version.is_v0_8() {
+        let ty = wrapped(from_ty, &wrappers);
+
+        return Ok(parse_quote! { #ty: Serialize<__S> });
+    }
+
+    let bound = match wrappers.split_first() {
+        Some((outer, inner)) => {
+            let inner_ty = wrapped(from_ty, inner);
+
+            parse_quote! { #outer: __rkyv::with::SerializeWith<#inner_ty, __S> }
+        }
+        None => parse_quote! { #from_ty: Serialize<__S> },
+    };
+
+    Ok(bound)
+}
+
+/// Bounds required to deserialize a field through [`deserialize_field`].
+fn deserialize_bounds(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    version: Version,
+) -> Result<[WherePredicate; 2]> {
+    let ty = with_ty(field, attrs, version)?;
+
+    if !version.is_v0_8() {
+        return Ok([
+            parse_quote! { #ty: Archive },
+            parse_quote! { Archived<#ty>: Deserialize<#ty, __D> },
+        ]);
+    }
+
+    let (from_ty, wrapper) = deserialize_wrapper(field, attrs, version)?;
+
+    let bound = match wrapper {
+        Some(wrapper) => parse_quote! {
+            #wrapper: __rkyv::with::DeserializeWith<
+                <#wrapper as __rkyv::with::ArchiveWith<#from_ty>>::Archived,
+                #from_ty,
+                __D,
+            >
+        },
+        None => parse_quote! { Archived<#from_ty>: Deserialize<#from_ty, __D> },
+    };
+
+    Ok([archive_bound(field, attrs, version)?, bound])
+}
+
+/// Whether the remote type of the field borrows its data from the archive.
+///
+/// Only `&str` and `&[T]` can be borrowed since their archived counterparts,
+/// e.g. `ArchivedString` or `ArchivedVec<T>`, dereference to them directly.
+pub fn is_borrowed(field: &Field, attrs: &ParsedAttributes) -> Result<bool> {
+    let from_ty = attrs.from.as_ref().unwrap_or(&field.ty);
+
+    let Type::Reference(reference) = from_ty else {
+        return Ok(false);
+    };
+
+    match *reference.elem {
+        _ if reference.mutability.is_some() => {}
+        Type::Path(ref path) if path.qself.is_none() && path.path.is_ident("str") => {
+            return Ok(true)
+        }
+        Type::Slice(_) => return Ok(true),
+        _ => {}
+    }
+
+    let msg = "only `&str` and `&[T]` fields can borrow from the archive";
+
+    Err(Error::new_spanned(from_ty, msg))
+}
+
+/// Deserializes a reference to the archived field back into the type that it
+/// was archived from.
+///
+/// Borrowed fields, see [`is_borrowed`], dereference the archived field
+/// instead. For rkyv 0.7 the archived field deserializes into the wrapped type which
+/// then needs to be unwrapped again, whereas for rkyv 0.8 the wrapper
+/// deserializes the archived field directly.
+pub fn deserialize_field(
+    field: &Field,
+    attrs: &ParsedAttributes,
+    archived: Expr,
+    version: Version,
+) -> Result<Expr> {
+    let ty = with_ty(field, attrs, version)?;
+
+    if is_borrowed(field, attrs)? {
+        return Ok(parse_quote! { ::core::ops::Deref::deref(#archived) });
+    }
+
+    if !version.is_v0_8() {
+        let value = parse_quote! { Deserialize::<#ty, __D>::deserialize(#archived, deserializer)? };
+
+        return with_inner(field, attrs, value, version);
+    }
+
+    let (from_ty, wrapper) = deserialize_wrapper(field, attrs, version)?;
+
+    let value = match wrapper {
+        Some(wrapper) => parse_quote! {
+            <#wrapper as __rkyv::with::DeserializeWith<_, #from_ty, __D>>::deserialize_with(
+                #archived,
+                deserializer,
+            )?
+        },
+        None => {
+            parse_quote! { Deserialize::<#from_ty, __D>::deserialize(#archived, deserializer)? }
+        }
+    };
+
+    Ok(value)
 }
 
 pub fn strip_raw(ident: &Ident) -> String {
@@ -0,0 +1,528 @@
+//! Code generation for the parts of rkyv's API whose shape differs between
+//! the supported rkyv versions.
+//!
+//! The version is picked per derive through the top level attribute
+//! `rkyv = "..."` and defaults to rkyv 0.7, or to rkyv 0.8 if the feature
+//! `rkyv-0_8` is enabled.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parenthesized, parse_quote, token, Attribute, Error, Expr, Ident, LitStr, Member, Path, Result,
+    Token, Type, WherePredicate,
+};
+
+use crate::util::PunctuatedExt;
+
+/// The rkyv version that the generated code targets.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Version {
+    V0_7,
+    V0_8,
+}
+
+impl Default for Version {
+    /// Features are additive so `rkyv-0_8` takes precedence over `rkyv-0_7`
+    /// and neither of them is required.
+    fn default() -> Self {
+        if cfg!(feature = "rkyv-0_8") {
+            Self::V0_8
+        } else {
+            Self::V0_7
+        }
+    }
+}
+
+impl Version {
+    /// Parses the version of `rkyv = "..."`.
+    pub fn parse(lit: &LitStr) -> Result<Self> {
+        match lit.value().as_str() {
+            "0.7" => Ok(Self::V0_7),
+            "0.8" => Ok(Self::V0_8),
+            _ => Err(Error::new_spanned(lit, "expected `\"0.7\"` or `\"0.8\"`")),
+        }
+    }
+
+    pub fn is_v0_8(self) -> bool {
+        self == Self::V0_8
+    }
+
+    /// Path to rkyv's `Fallible` trait.
+    pub fn fallible(self) -> Path {
+        if self.is_v0_8() {
+            parse_quote! { __rkyv::rancor::Fallible }
+        } else {
+            parse_quote! { __rkyv::Fallible }
+        }
+    }
+
+    /// The `resolve_with` method of the `ArchiveWith` trait.
+    ///
+    /// Within `body`, the output is available as `out` and, for rkyv 0.7, its
+    /// position as `pos`.
+    pub fn resolve_with(self, from_ty: &Type, body: TokenStream) -> TokenStream {
+        if self.is_v0_8() {
+            quote! {
+                #[allow(clippy::unit_arg)]
+                #[inline]
+                fn resolve_with(
+                    field: &#from_ty,
+                    resolver: Self::Resolver,
+                    out: __rkyv::Place<Self::Archived>,
+                ) {
+                    #body
+                }
+            }
+        } else {
+            quote! {
+                #[allow(clippy::unit_arg)]
+                #[inline]
+                unsafe fn resolve_with(
+                    field: &#from_ty,
+                    pos: usize,
+                    resolver: Self::Resolver,
+                    out: *mut Self::Archived,
+                ) {
+                    #body
+                }
+            }
+        }
+    }
+
+    /// Resolves `value` into the whole of `out`.
+    pub fn resolve(self, value: &Expr, resolver: &Expr) -> TokenStream {
+        if self.is_v0_8() {
+            quote! { __rkyv::Archive::resolve(#value, #resolver, out); }
+        } else {
+            quote! { __rkyv::Archive::resolve(#value, pos, #resolver, out); }
+        }
+    }
+
+    /// Resolves `value` into the field `member` of `out`.
+    pub fn resolve_field(self, member: &Member, value: &Expr, resolver: &Expr) -> TokenStream {
+        if self.is_v0_8() {
+            quote! {
+                let field_ptr = unsafe { ::core::ptr::addr_of_mut!((*out.ptr()).#member) };
+                let out_field = unsafe { __rkyv::Place::from_field_unchecked(out, field_ptr) };
+                __rkyv::Archive::resolve(#value, #resolver, out_field);
+            }
+        } else {
+            quote! {
+                let (fp, fo) = __rkyv::out_field!(out.#member);
+                __rkyv::Archive::resolve(#value, pos + fp, #resolver, fo);
+            }
+        }
+    }
+
+    /// Shadows `out` with a cast to the archived variant struct `archived_variant`
+    /// and writes the tag of `variant` into its `tag` member.
+    pub fn resolve_variant_tag(
+        self,
+        archived_variant: TokenStream,
+        tag: &Member,
+        variant: &Ident,
+    ) -> TokenStream {
+        if self.is_v0_8() {
+            quote! {
+                let out = unsafe { out.cast_unchecked::<#archived_variant>() };
+                unsafe {
+                    ::core::ptr::addr_of_mut!((*out.ptr()).#tag).write(ArchivedTag::#variant);
+                }
+            }
+        } else {
+            quote! {
+                let out = out.cast::<#archived_variant>();
+                ::core::ptr::addr_of_mut!((*out).#tag).write(ArchivedTag::#variant);
+            }
+        }
+    }
+
+    /// Writes the tag of the unit variant `variant` into `out`.
+    pub fn resolve_unit_variant(self, variant: &Ident) -> TokenStream {
+        if self.is_v0_8() {
+            quote! {
+                unsafe {
+                    out.cast_unchecked::<ArchivedTag>().write_unchecked(ArchivedTag::#variant);
+                }
+            }
+        } else {
+            quote! {
+                out.cast::<ArchivedTag>().write(ArchivedTag::#variant);
+            }
+        }
+    }
+
+    /// Creates a `Default` instance of `error_ty` and converts it into an error of
+    /// the serializer `__S`, alongside the bounds this requires.
+    ///
+    /// rkyv 0.7 requires the serializer's error to implement `From<error_ty>`
+    /// whereas rkyv 0.8 requires it to implement `rancor::Source`.
+    pub fn serializer_error(self, error_ty: &Type) -> (Expr, [WherePredicate; 2]) {
+        if self.is_v0_8() {
+            let expr = parse_quote! {
+                <<__S as Fallible>::Error as __rkyv::rancor::Source>::new(
+                    <#error_ty as ::core::default::Default>::default()
+                )
+            };
+
+            let bounds = [
+                parse_quote! { <__S as Fallible>::Error: __rkyv::rancor::Source },
+                parse_quote! {
+                    #error_ty: ::core::default::Default
+                        + ::core::error::Error
+                        + ::core::marker::Send
+                        + ::core::marker::Sync
+                        + 'static
+                },
+            ];
+
+            (expr, bounds)
+        } else {
+            let expr = parse_quote! {
+                <<__S as Fallible>::Error as ::core::convert::From<#error_ty>>::from(
+                    <#error_ty as ::core::default::Default>::default()
+                )
+            };
+
+            let bounds = [
+                parse_quote! { <__S as Fallible>::Error: ::core::convert::From<#error_ty> },
+                parse_quote! { #error_ty: ::core::default::Default },
+            ];
+
+            (expr, bounds)
+        }
+    }
+
+    /// Converts the error `error` of type `error_ty` into an error of the
+    /// serializer or deserializer `fallible`, alongside the bounds this requires.
+    ///
+    /// The error type is only optional for rkyv 0.8 whose errors can be created
+    /// from any error.
+    pub fn error_into(
+        self,
+        fallible: &Ident,
+        error: Expr,
+        error_ty: Option<&Type>,
+    ) -> (Expr, Vec<WherePredicate>) {
+        if self.is_v0_8() {
+            let expr = parse_quote! {
+                <<#fallible as Fallible>::Error as __rkyv::rancor::Source>::new(#error)
+            };
+
+            let mut bounds = vec![parse_quote! {
+                <#fallible as Fallible>::Error: __rkyv::rancor::Source
+            }];
+
+            if let Some(error_ty) = error_ty {
+                bounds.push(parse_quote! {
+                    #error_ty: ::core::error::Error
+                        + ::core::marker::Send
+                        + ::core::marker::Sync
+                        + 'static
+                });
+            }
+
+            (expr, bounds)
+        } else {
+            let expr = parse_quote! {
+                <<#fallible as Fallible>::Error as ::core::convert::From<#error_ty>>::from(#error)
+            };
+
+            let bounds = vec![parse_quote! {
+                <#fallible as Fallible>::Error: ::core::convert::From<#error_ty>
+            }];
+
+            (expr, bounds)
+        }
+    }
+
+    /// Defines the trait `GetterResult` which turns the return value of fallible
+    /// getters into a `Result`.
+    ///
+    /// For rkyv 0.8, getters that return `None` fail with the error
+    /// `MissingValue`. rkyv 0.7 serializer errors can only be created from
+    /// types that are nameable by the user so getters that return `None` fail
+    /// with the `Default` value of the error type of `fallible = "..."`
+    /// instead.
+    pub fn getter_result_items(self) -> TokenStream {
+        if !self.is_v0_8() {
+            return quote! {
+                trait GetterResult<T, E> {
+                    fn into_result(self) -> ::core::result::Result<T, E>;
+                }
+
+                impl<T, E> GetterResult<T, E> for ::core::result::Result<T, E> {
+                    fn into_result(self) -> ::core::result::Result<T, E> {
+                        self
+                    }
+                }
+
+                impl<T, E: ::core::default::Default> GetterResult<T, E> for ::core::option::Option<T> {
+                    fn into_result(self) -> ::core::result::Result<T, E> {
+                        self.ok_or_else(E::default)
+                    }
+                }
+            };
+        }
+
+        quote! {
+            #[derive(Debug)]
+            struct MissingValue;
+
+            impl ::core::fmt::Display for MissingValue {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    f.write_str("getter returned no value")
+                }
+            }
+
+            impl ::core::error::Error for MissingValue {}
+
+            trait GetterResult<T> {
+                type Error;
+
+                fn into_result(self) -> ::core::result::Result<T, Self::Error>;
+            }
+
+            impl<T, E> GetterResult<T> for ::core::result::Result<T, E> {
+                type Error = E;
+
+                fn into_result(self) -> ::core::result::Result<T, E> {
+                    self
+                }
+            }
+
+            impl<T> GetterResult<T> for ::core::option::Option<T> {
+                type Error = MissingValue;
+
+                fn into_result(self) -> ::core::result::Result<T, MissingValue> {
+                    self.ok_or(MissingValue)
+                }
+            }
+        }
+    }
+
+    /// Turns the return value `value` of a fallible getter with the error type
+    /// `error_ty` of `fallible = "..."` into a `Result`, see
+    /// [`getter_result_items`](Self::getter_result_items).
+    pub fn getter_result(self, value: Expr, error_ty: Option<&Type>) -> Expr {
+        if self.is_v0_8() {
+            parse_quote! { GetterResult::into_result(#value) }
+        } else {
+            parse_quote! { GetterResult::<_, #error_ty>::into_result(#value) }
+        }
+    }
+
+    /// Defines the wrapper `BorrowedStr` which archives a `str` as a `String`.
+    ///
+    /// The `SerializeWith` impl is only included if `with_serialize` is set.
+    pub fn str_wrapper(self, with_serialize: bool) -> TokenStream {
+        let archive_impl = if self.is_v0_8() {
+            quote! {
+                impl __rkyv::with::ArchiveWith<str> for BorrowedStr {
+                    type Archived = __rkyv::string::ArchivedString;
+                    type Resolver = __rkyv::string::StringResolver;
+
+                    fn resolve_with(
+                        field: &str,
+                        resolver: Self::Resolver,
+                        out: __rkyv::Place<Self::Archived>,
+                    ) {
+                        __rkyv::string::ArchivedString::resolve_from_str(field, resolver, out);
+                    }
+                }
+            }
+        } else {
+            quote! {
+                impl __rkyv::with::ArchiveWith<str> for BorrowedStr {
+                    type Archived = __rkyv::string::ArchivedString;
+                    type Resolver = __rkyv::string::StringResolver;
+
+                    unsafe fn resolve_with(
+                        field: &str,
+                        pos: usize,
+                        resolver: Self::Resolver,
+                        out: *mut Self::Archived,
+                    ) {
+                        __rkyv::string::ArchivedString::resolve_from_str(field, pos, resolver, out);
+                    }
+                }
+            }
+        };
+
+        let serialize_impl = with_serialize.then(|| {
+            let fallible = self.fallible();
+            let source_bound = self
+                .is_v0_8()
+                .then(|| quote! { <__S as #fallible>::Error: __rkyv::rancor::Source, });
+
+            quote! {
+                impl<__S: #fallible + ?Sized> __rkyv::with::SerializeWith<str, __S> for BorrowedStr
+                where
+                    #source_bound
+                    str: __rkyv::SerializeUnsized<__S>,
+                {
+                    fn serialize_with(
+                        field: &str,
+                        serializer: &mut __S,
+                    ) -> ::core::result::Result<Self::Resolver, <__S as #fallible>::Error> {
+                        __rkyv::string::ArchivedString::serialize_from_str(field, serializer)
+                    }
+                }
+            }
+        });
+
+        quote! {
+            struct BorrowedStr;
+
+            #archive_impl
+            #serialize_impl
+        }
+    }
+
+    /// Defines the wrapper `BorrowedSlice` which archives a slice as a `Vec`.
+    ///
+    /// The `SerializeWith` impl is only included if `with_serialize` is set.
+    pub fn slice_wrapper(self, with_serialize: bool) -> TokenStream {
+        let archive_impl = if self.is_v0_8() {
+            quote! {
+                impl<U: __rkyv::Archive> __rkyv::with::ArchiveWith<[U]> for BorrowedSlice {
+                    type Archived = __rkyv::vec::ArchivedVec<__rkyv::Archived<U>>;
+                    type Resolver = __rkyv::vec::VecResolver;
+
+                    fn resolve_with(
+                        field: &[U],
+                        resolver: Self::Resolver,
+                        out: __rkyv::Place<Self::Archived>,
+                    ) {
+                        __rkyv::vec::ArchivedVec::resolve_from_slice(field, resolver, out);
+                    }
+                }
+            }
+        } else {
+            quote! {
+                impl<U: __rkyv::Archive> __rkyv::with::ArchiveWith<[U]> for BorrowedSlice {
+                    type Archived = __rkyv::vec::ArchivedVec<__rkyv::Archived<U>>;
+                    type Resolver = __rkyv::vec::VecResolver;
+
+                    unsafe fn resolve_with(
+                        field: &[U],
+                        pos: usize,
+                        resolver: Self::Resolver,
+                        out: *mut Self::Archived,
+                    ) {
+                        __rkyv::vec::ArchivedVec::resolve_from_slice(field, pos, resolver, out);
+                    }
+                }
+            }
+        };
+
+        let serialize_impl = with_serialize.then(|| {
+            let fallible = self.fallible();
+            let (serializer_bound, slice_bound) = if self.is_v0_8() {
+                (
+                    quote! { #fallible + __rkyv::ser::Allocator + __rkyv::ser::Writer + ?Sized },
+                    None,
+                )
+            } else {
+                (
+                    quote! { __rkyv::ser::Serializer + ?Sized },
+                    Some(quote! { [U]: __rkyv::SerializeUnsized<__S>, }),
+                )
+            };
+
+            quote! {
+                impl<U, __S> __rkyv::with::SerializeWith<[U], __S> for BorrowedSlice
+                where
+                    U: __rkyv::Serialize<__S>,
+                    __S: #serializer_bound,
+                    #slice_bound
+                {
+                    fn serialize_with(
+                        field: &[U],
+                        serializer: &mut __S,
+                    ) -> ::core::result::Result<Self::Resolver, <__S as #fallible>::Error> {
+                        __rkyv::vec::ArchivedVec::serialize_from_slice(field, serializer)
+                    }
+                }
+            }
+        });
+
+        quote! {
+            struct BorrowedSlice;
+
+            #archive_impl
+            #serialize_impl
+        }
+    }
+
+    /// Defines the error `UnmappedVariant` for variants of a remote enum that are
+    /// not mapped by the enum `name`, alongside the expression that converts it
+    /// into an error of the serializer `__S` and the bound this requires.
+    ///
+    /// Returns `None` for rkyv 0.7 because its serializer errors can only be
+    /// created from types that are nameable by the user.
+    pub fn unmapped_variant_error(
+        self,
+        name: &Ident,
+    ) -> Option<(TokenStream, Expr, WherePredicate)> {
+        if !self.is_v0_8() {
+            return None;
+        }
+
+        let msg = format!("variant of the remote type is not mapped by `{name}`");
+
+        let definition = quote! {
+            #[derive(Debug)]
+            struct UnmappedVariant;
+
+            impl ::core::fmt::Display for UnmappedVariant {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    f.write_str(#msg)
+                }
+            }
+
+            impl ::core::error::Error for UnmappedVariant {}
+        };
+
+        let expr = parse_quote! {
+            <<__S as Fallible>::Error as __rkyv::rancor::Source>::new(UnmappedVariant)
+        };
+
+        let bound = parse_quote! { <__S as Fallible>::Error: __rkyv::rancor::Source };
+
+        Some((definition, expr, bound))
+    }
+
+    /// Parses the wrapper types of rkyv's own field attribute, i.e. `#[with(...)]`
+    /// for rkyv 0.7 and `#[rkyv(with = ...)]` for rkyv 0.8.
+    ///
+    /// Returns `None` if the attribute is not rkyv's.
+    pub fn with_wrappers(self, attr: &Attribute) -> Option<Result<Vec<Type>>> {
+        if self.is_v0_8() {
+            if !attr.path().is_ident("rkyv") {
+                return None;
+            }
+
+            let mut wrappers = Vec::new();
+
+            let res = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("with") {
+                    wrappers.push(meta.value()?.parse()?);
+                } else if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<Type>()?;
+                } else if meta.input.peek(token::Paren) {
+                    let _content;
+                    parenthesized!(_content in meta.input);
+                }
+
+                Ok(())
+            });
+
+            Some(res.map(|_| wrappers))
+        } else if attr.path().is_ident("with") {
+            Some(attr.parse_args_with(Vec::parse_separated_nonempty::<Token![,]>))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,1091 @@
+/// Tests whose code is the same for all rkyv versions.
+///
+/// The version is picked through `rkyv = $version` on every type. The
+/// invoking test crate provides the imports and the helpers `serialize`,
+/// `archive`, `convert`, and `roundtrip` for its version.
+macro_rules! shared_tests {
+    ($version:literal) => {
+        #[test]
+        fn unit_struct() {
+            #[derive(Debug, PartialEq)]
+            struct Remote;
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote))]
+            struct Example;
+
+            let remote = Remote;
+            roundtrip::<Example, _>(&remote);
+        }
+
+        #[test]
+        fn named_struct_private() {
+            mod remote {
+                #[derive(Copy, Clone, Default)]
+                pub struct Remote {
+                    inner: [u8; 4],
+                }
+
+                impl Remote {
+                    pub fn into_inner(self) -> [u8; 4] {
+                        self.inner
+                    }
+
+                    #[allow(clippy::wrong_self_convention)]
+                    pub fn to_inner(&self) -> [u8; 4] {
+                        self.inner
+                    }
+
+                    pub fn as_inner(&self) -> &[u8; 4] {
+                        &self.inner
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct ExampleByVal {
+                #[archive_with(getter = "remote::Remote::into_inner", getter_owned)]
+                inner: [u8; 4],
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct ExampleByRef {
+                #[archive_with(getter = "remote::Remote::to_inner")]
+                inner: [u8; 4],
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct ExampleThroughRef {
+                #[archive_with(getter = "remote::Remote::as_inner")]
+                inner: [u8; 4],
+            }
+
+            let remote = remote::Remote::default();
+            let _ = archive::<ExampleByVal, _>(&serialize::<ExampleByVal, _>(&remote));
+            let _ = archive::<ExampleByRef, _>(&serialize::<ExampleByRef, _>(&remote));
+            let _ = archive::<ExampleThroughRef, _>(&serialize::<ExampleThroughRef, _>(&remote));
+        }
+
+        #[test]
+        fn unnamed_struct_private() {
+            mod remote {
+                #[derive(Copy, Clone, Default)]
+                pub struct Remote([u8; 4]);
+
+                impl Remote {
+                    pub fn into_inner(self) -> [u8; 4] {
+                        self.0
+                    }
+
+                    pub fn as_inner(&self) -> [u8; 4] {
+                        self.0
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct ExampleByRef(#[archive_with(getter = "remote::Remote::as_inner")] [u8; 4]);
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct ExampleByVal(
+                #[archive_with(getter = "remote::Remote::into_inner", getter_owned)] [u8; 4],
+            );
+
+            let remote = remote::Remote::default();
+            let _ = archive::<ExampleByRef, _>(&serialize::<ExampleByRef, _>(&remote));
+            let _ = archive::<ExampleByVal, _>(&serialize::<ExampleByVal, _>(&remote));
+        }
+
+        #[test]
+        fn constructor() {
+            mod remote {
+                #[derive(Debug, PartialEq)]
+                pub struct Remote {
+                    pub a: u8,
+                    b: Vec<u8>,
+                }
+
+                impl Remote {
+                    pub fn new(b: Vec<u8>, a: u8) -> Self {
+                        Self { a, b }
+                    }
+
+                    pub fn b(&self) -> &Vec<u8> {
+                        &self.b
+                    }
+                }
+
+                #[derive(Debug, PartialEq)]
+                pub struct RemoteUnnamed(u8, String);
+
+                impl RemoteUnnamed {
+                    pub fn new(a: u8, b: String) -> Self {
+                        Self(a, b)
+                    }
+
+                    pub fn a(&self) -> u8 {
+                        self.0
+                    }
+
+                    pub fn b(&self) -> &String {
+                        &self.1
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(
+                rkyv = $version,
+                from(remote::Remote),
+                constructor = "remote::Remote::new"
+            )]
+            struct Example {
+                #[archive_with(arg = 1)]
+                a: u8,
+                #[archive_with(getter = "remote::Remote::b")]
+                b: Vec<u8>,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(
+                rkyv = $version,
+                from(remote::RemoteUnnamed),
+                constructor = "remote::RemoteUnnamed::new"
+            )]
+            struct ExampleUnnamed(
+                #[archive_with(getter = "remote::RemoteUnnamed::a")] u8,
+                #[archive_with(getter = "remote::RemoteUnnamed::b")] String,
+            );
+
+            roundtrip::<Example, _>(&remote::Remote::new(vec![1, 2], 3));
+            roundtrip::<ExampleUnnamed, _>(&remote::RemoteUnnamed::new(1, "a".to_owned()));
+        }
+
+        #[test]
+        fn setter() {
+            mod remote {
+                #[derive(Debug, Default, PartialEq)]
+                pub struct Remote {
+                    pub a: u8,
+                    b: Vec<u8>,
+                    c: String,
+                }
+
+                impl Remote {
+                    pub fn empty() -> Self {
+                        Self::default()
+                    }
+
+                    pub fn b(&self) -> &Vec<u8> {
+                        &self.b
+                    }
+
+                    pub fn set_b(&mut self, b: Vec<u8>) {
+                        self.b = b;
+                    }
+
+                    pub fn c(&self) -> &String {
+                        &self.c
+                    }
+
+                    pub fn with_c(self, c: String) -> Self {
+                        Self { c, ..self }
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct Example {
+                a: u8,
+                #[archive_with(getter = "remote::Remote::b", setter = "remote::Remote::set_b")]
+                b: Vec<u8>,
+                #[archive_with(
+                    getter = "remote::Remote::c",
+                    setter = "remote::Remote::with_c",
+                    setter_owned
+                )]
+                c: String,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote), default = "remote::Remote::empty")]
+            struct ExampleBase {
+                a: u8,
+                #[archive_with(getter = "remote::Remote::b", setter = "remote::Remote::set_b")]
+                b: Vec<u8>,
+                #[archive_with(
+                    getter = "remote::Remote::c",
+                    setter = "remote::Remote::with_c",
+                    setter_owned
+                )]
+                c: String,
+            }
+
+            let mut remote = remote::Remote::default().with_c("c".to_owned());
+            remote.a = 1;
+            remote.set_b(vec![2, 3]);
+
+            roundtrip::<Example, _>(&remote);
+            roundtrip::<ExampleBase, _>(&remote);
+        }
+
+        #[test]
+        fn default_values() {
+            #[derive(Debug, Default, PartialEq)]
+            struct Remote {
+                a: u8,
+                b: Vec<u8>,
+                c: String,
+            }
+
+            fn base() -> Remote {
+                Remote {
+                    a: 0,
+                    b: vec![1],
+                    c: "base".to_owned(),
+                }
+            }
+
+            fn c() -> String {
+                "omitted".to_owned()
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote), default)]
+            struct ExampleDefault {
+                a: u8,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote), default = "base")]
+            struct ExampleBase {
+                a: u8,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote), default, omitted(c = "c"))]
+            struct ExampleOmitted {
+                a: u8,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote), omitted(b = "Vec::new", c = "c"))]
+            struct ExampleOmittedOnly {
+                a: u8,
+            }
+
+            #[derive(Debug, Default, PartialEq)]
+            struct RemoteUnnamed(u8, String);
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(RemoteUnnamed), default)]
+            struct ExampleUnnamed(u8);
+
+            let remote = Remote {
+                a: 1,
+                ..Default::default()
+            };
+
+            roundtrip::<ExampleDefault, _>(&remote);
+            roundtrip::<ExampleBase, _>(&Remote { a: 1, ..base() });
+            roundtrip::<ExampleOmitted, _>(&Remote { c: c(), ..remote });
+
+            let remote = Remote {
+                a: 1,
+                b: Vec::new(),
+                c: c(),
+            };
+
+            roundtrip::<ExampleOmittedOnly, _>(&remote);
+            roundtrip::<ExampleUnnamed, _>(&RemoteUnnamed(1, String::new()));
+        }
+
+        #[test]
+        fn rename() {
+            #[derive(Debug, PartialEq)]
+            struct Remote {
+                a: u8,
+                r#type: String,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote))]
+            struct Example {
+                #[archive_with(rename = "a")]
+                renamed: u8,
+                #[archive_with(rename = "r#type")]
+                kind: String,
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct RemoteUnnamed(u8, String);
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(RemoteUnnamed))]
+            struct ExampleUnnamed(
+                #[archive_with(rename = "1")] String,
+                #[archive_with(rename = "0")] u8,
+            );
+
+            #[derive(Debug, PartialEq)]
+            enum RemoteEnum {
+                A { a: u8, b: String },
+                B(u8, String),
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(RemoteEnum))]
+            enum ExampleEnum {
+                A {
+                    #[archive_with(rename = "a")]
+                    renamed: u8,
+                    b: String,
+                },
+                B(
+                    #[archive_with(rename = "1")] String,
+                    #[archive_with(rename = "0")] u8,
+                ),
+            }
+
+            roundtrip::<Example, _>(&Remote {
+                a: 1,
+                r#type: "a".to_owned(),
+            });
+            roundtrip::<ExampleUnnamed, _>(&RemoteUnnamed(1, "a".to_owned()));
+
+            for remote in [
+                RemoteEnum::A {
+                    a: 1,
+                    b: "a".to_owned(),
+                },
+                RemoteEnum::B(1, "a".to_owned()),
+            ] {
+                roundtrip::<ExampleEnum, _>(&remote);
+            }
+        }
+
+        #[test]
+        fn variant_rename() {
+            #[derive(Debug, PartialEq)]
+            enum Remote {
+                Current,
+                Legacy,
+                Value { a: u8 },
+                Pair(u8, String),
+                OldPair(u8, String),
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote))]
+            enum Example {
+                #[archive_with(rename = "Current", alias = "Legacy")]
+                Stable,
+                #[archive_with(rename = "Value")]
+                Renamed { a: u8 },
+                #[archive_with(alias = "OldPair")]
+                Pair(u8, String),
+            }
+
+            roundtrip::<Example, _>(&Remote::Current);
+            roundtrip::<Example, _>(&Remote::Value { a: 1 });
+            roundtrip::<Example, _>(&Remote::Pair(1, "a".to_owned()));
+
+            assert_eq!(convert::<Example, _>(&Remote::Legacy), Remote::Current);
+            assert_eq!(
+                convert::<Example, _>(&Remote::OldPair(1, "a".to_owned())),
+                Remote::Pair(1, "a".to_owned())
+            );
+        }
+
+        #[test]
+        fn other_variant_without_counterpart() {
+            #[allow(unused)]
+            enum Remote {
+                A,
+                B,
+                C,
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(Remote))]
+            enum Example {
+                A,
+                #[archive_with(other)]
+                Unknown,
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(Remote), exhaustive)]
+            enum Exhaustive {
+                A,
+                B,
+                #[archive_with(other)]
+                Unknown,
+            }
+
+            let bytes = serialize::<Example, _>(&Remote::A);
+            let archived = archive::<Example, Remote>(&bytes);
+            assert!(matches!(archived, ArchivedExample::A));
+
+            for remote in [Remote::B, Remote::C] {
+                let bytes = serialize::<Example, _>(&remote);
+                let archived = archive::<Example, Remote>(&bytes);
+                assert!(matches!(archived, ArchivedExample::Unknown));
+            }
+
+            let bytes = serialize::<Exhaustive, _>(&Remote::C);
+            let archived = archive::<Exhaustive, Remote>(&bytes);
+            assert!(matches!(archived, ArchivedExhaustive::Unknown));
+        }
+
+        #[test]
+        fn exhaustive() {
+            #[derive(Debug, PartialEq)]
+            struct Remote {
+                a: u8,
+                b: PathBuf,
+                c: String,
+                d: Vec<u8>,
+            }
+
+            impl Remote {
+                fn len(&self) -> usize {
+                    self.d.len()
+                }
+            }
+
+            fn empty() -> Vec<u8> {
+                Vec::new()
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote), exhaustive, omitted(d = "empty"))]
+            struct Example {
+                a: u8,
+                #[archive_with(from(PathBuf), via(AsString))]
+                b: String,
+                #[archive_with(rename = "c")]
+                renamed: String,
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(
+                rkyv = $version,
+                from(Remote),
+                exhaustive,
+                omitted(a = "u8::default", b = "PathBuf::new")
+            )]
+            struct ExampleGetter {
+                c: String,
+                #[archive_with(rename = "d", getter = "Remote::len")]
+                len: usize,
+            }
+
+            mod private {
+                pub struct Remote {
+                    pub a: u8,
+                    b: u8,
+                }
+
+                impl Remote {
+                    pub fn new(a: u8, b: u8) -> Self {
+                        Self { a, b }
+                    }
+
+                    pub fn b(&self) -> u8 {
+                        self.b
+                    }
+                }
+            }
+
+            // Private fields are covered by `..` due to the getter
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(private::Remote), exhaustive)]
+            struct ExamplePrivate {
+                a: u8,
+                #[archive_with(getter = "private::Remote::b")]
+                b: u8,
+            }
+
+            #[derive(Debug, PartialEq)]
+            enum RemoteEnum {
+                A,
+                B(u8, String),
+                C { a: Vec<u8> },
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(RemoteEnum), exhaustive)]
+            enum ExampleEnum {
+                A,
+                B(u8, String),
+                #[archive_with(rename = "C")]
+                Renamed {
+                    a: Vec<u8>,
+                },
+            }
+
+            let remote = Remote {
+                a: 1,
+                b: PathBuf::from("b"),
+                c: "c".to_owned(),
+                d: Vec::new(),
+            };
+
+            roundtrip::<Example, _>(&remote);
+            let _ = archive::<ExampleGetter, _>(&serialize::<ExampleGetter, _>(&remote));
+
+            let remote = private::Remote::new(1, 2);
+            let bytes = serialize::<ExamplePrivate, _>(&remote);
+            let archived = archive::<ExamplePrivate, private::Remote>(&bytes);
+            assert_eq!(archived.a, 1);
+            assert_eq!(archived.b, 2);
+
+            for remote in [
+                RemoteEnum::A,
+                RemoteEnum::B(1, "b".to_owned()),
+                RemoteEnum::C { a: vec![1] },
+            ] {
+                roundtrip::<ExampleEnum, _>(&remote);
+            }
+        }
+
+        #[test]
+        fn bound() {
+            #[derive(Debug, PartialEq)]
+            struct Remote<T> {
+                a: Option<T>,
+                b: u8,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version,
+                from(Remote::<T>),
+                bound(
+                    archive = "T: rkyv::Archive",
+                    serialize = "T: rkyv::Serialize<__S>",
+                    deserialize = "T: rkyv::Archive, rkyv::Archived<T>: rkyv::Deserialize<T, __D>",
+                )
+            )]
+            struct Example<T> {
+                a: Option<T>,
+                b: u8,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote::<T>))]
+            struct ExampleField<T> {
+                #[archive_with(bound(
+                    archive = "T: rkyv::Archive",
+                    serialize = "T: rkyv::Serialize<__S>",
+                    deserialize = "T: rkyv::Archive, rkyv::Archived<T>: rkyv::Deserialize<T, __D>",
+                ))]
+                a: Option<T>,
+                b: u8,
+            }
+
+            let remote = Remote {
+                a: Some(1_u32),
+                b: 2,
+            };
+
+            roundtrip::<Example<u32>, _>(&remote);
+            roundtrip::<ExampleField<u32>, _>(&remote);
+        }
+
+        #[test]
+        fn generics() {
+            #[derive(Debug, PartialEq)]
+            struct Remote<'a, T, const N: usize, U = u8> {
+                a: [T; N],
+                b: Vec<U>,
+                c: PhantomData<&'a ()>,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote<'a, T, N, U>))]
+            struct Example<'a, T, const N: usize, U = u8> {
+                a: [T; N],
+                b: Vec<U>,
+                c: PhantomData<&'a ()>,
+            }
+
+            #[derive(Debug, PartialEq)]
+            enum RemoteEnum<'a, T, const N: usize> {
+                A([T; N]),
+                B { b: PhantomData<&'a ()> },
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(RemoteEnum<'a, T, N>))]
+            enum ExampleEnum<'a, T, const N: usize> {
+                A([T; N]),
+                B { b: PhantomData<&'a ()> },
+            }
+
+            let remote = Remote {
+                a: [1_u32, 2],
+                b: vec![3_u8],
+                c: PhantomData,
+            };
+
+            roundtrip::<Example<u32, 2>, _>(&remote);
+
+            for remote in [RemoteEnum::A([1_u16; 3]), RemoteEnum::B { b: PhantomData }] {
+                roundtrip::<ExampleEnum<u16, 3>, _>(&remote);
+            }
+        }
+
+        #[test]
+        fn transparent() {
+            mod remote {
+                use std::sync::atomic::{AtomicUsize, Ordering};
+
+                pub static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct UserId(u64);
+
+                impl From<u64> for UserId {
+                    fn from(id: u64) -> Self {
+                        Self(id)
+                    }
+                }
+
+                impl From<UserId> for u64 {
+                    fn from(id: UserId) -> Self {
+                        CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+
+                        id.0
+                    }
+                }
+
+                #[derive(Debug, PartialEq)]
+                pub struct Name(String);
+
+                impl From<String> for Name {
+                    fn from(name: String) -> Self {
+                        Self(name)
+                    }
+                }
+
+                impl AsRef<String> for Name {
+                    fn as_ref(&self) -> &String {
+                        &self.0
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(remote::UserId), transparent)]
+            struct UserId(u64);
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(remote::Name), transparent(as_ref))]
+            struct Name {
+                name: String,
+            }
+
+            roundtrip::<UserId, _>(&remote::UserId::from(1));
+            assert_eq!(remote::CONVERSIONS.load(Ordering::Relaxed), 1);
+            roundtrip::<Name, _>(&remote::Name::from("name".to_owned()));
+        }
+
+        #[test]
+        fn field_functions() {
+            static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+            fn ip_to_bits(ip: &Ipv4Addr) -> u32 {
+                CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+
+                u32::from(*ip)
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct Remote<A> {
+                ip: Ipv4Addr,
+                ip_converted: u32,
+                values: Vec<A>,
+            }
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote::<A>))]
+            struct Example<A> {
+                #[archive_with(
+                    from(Ipv4Addr),
+                    serialize_fn = "ip_to_bits",
+                    deserialize_fn = "Ipv4Addr::from"
+                )]
+                ip: u32,
+                // Must not collide with the converted value of `ip`
+                ip_converted: u32,
+                values: Vec<A>,
+            }
+
+            #[derive(Debug, PartialEq)]
+            enum RemoteEnum {
+                A(Ipv4Addr),
+                B { ip: Ipv4Addr },
+            }
+
+            #[allow(unused)]
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(RemoteEnum))]
+            enum Enum {
+                A(
+                    #[archive_with(
+                        from(Ipv4Addr),
+                        serialize_fn = "ip_to_bits",
+                        deserialize_fn = "Ipv4Addr::from"
+                    )]
+                    u32,
+                ),
+                B {
+                    #[archive_with(
+                        from(Ipv4Addr),
+                        serialize_fn = "ip_to_bits",
+                        deserialize_fn = "Ipv4Addr::from"
+                    )]
+                    ip: u32,
+                },
+            }
+
+            let remote = Remote {
+                ip: Ipv4Addr::new(127, 0, 0, 1),
+                ip_converted: 1,
+                values: vec![1_u8, 2],
+            };
+
+            roundtrip::<Example<u8>, _>(&remote);
+            assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 1);
+
+            for remote in [
+                RemoteEnum::A(Ipv4Addr::LOCALHOST),
+                RemoteEnum::B {
+                    ip: Ipv4Addr::BROADCAST,
+                },
+            ] {
+                roundtrip::<Enum, _>(&remote);
+            }
+
+            assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 3);
+        }
+
+        #[test]
+        fn getter_calls() {
+            mod remote {
+                use std::cell::Cell;
+
+                pub struct Remote {
+                    name: String,
+                    tag: u8,
+                    pub calls: Cell<usize>,
+                    pub clones: Cell<usize>,
+                }
+
+                impl Clone for Remote {
+                    fn clone(&self) -> Self {
+                        self.clones.set(self.clones.get() + 1);
+
+                        Self::new(&self.name, self.tag)
+                    }
+                }
+
+                impl Remote {
+                    pub fn new(name: &str, tag: u8) -> Self {
+                        Self {
+                            name: name.to_owned(),
+                            tag,
+                            calls: Cell::new(0),
+                            clones: Cell::new(0),
+                        }
+                    }
+
+                    pub fn to_name(&self) -> String {
+                        self.calls.set(self.calls.get() + 1);
+
+                        self.name.clone()
+                    }
+
+                    pub fn tag(&self) -> &u8 {
+                        self.calls.set(self.calls.get() + 1);
+
+                        &self.tag
+                    }
+
+                    pub fn as_name(&self) -> &str {
+                        self.calls.set(self.calls.get() + 1);
+
+                        &self.name
+                    }
+
+                    pub fn into_name(self) -> String {
+                        self.name
+                    }
+
+                    pub fn into_tag(self) -> u8 {
+                        self.tag
+                    }
+                }
+
+                #[derive(Copy)]
+                pub struct Flags(pub u8);
+
+                #[allow(clippy::non_canonical_clone_impl)]
+                impl Clone for Flags {
+                    fn clone(&self) -> Self {
+                        panic!("`Flags` should be copied instead")
+                    }
+                }
+
+                impl Flags {
+                    pub fn into_bits(self) -> u8 {
+                        self.0
+                    }
+
+                    pub fn into_inverted(self) -> u8 {
+                        !self.0
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct Example {
+                #[archive_with(getter = "remote::Remote::to_name")]
+                name: String,
+                #[archive_with(getter = "remote::Remote::tag")]
+                tag: u8,
+                #[archive_with(getter = "remote::Remote::as_name", borrow(str))]
+                borrowed_name: String,
+                #[archive_with(getter = "remote::Remote::into_name", getter_owned)]
+                owned_name: String,
+                #[archive_with(getter = "remote::Remote::into_tag", getter_owned)]
+                owned_tag: u8,
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Flags))]
+            struct Flags(
+                #[archive_with(getter = "remote::Flags::into_bits", getter_owned)] u8,
+                #[archive_with(getter = "remote::Flags::into_inverted", getter_owned)] u8,
+            );
+
+            let remote = remote::Remote::new("name", 3);
+            let bytes = serialize::<Example, _>(&remote);
+            let archived = archive::<Example, remote::Remote>(&bytes);
+            assert_eq!(archived.name, "name");
+            assert_eq!(archived.tag, 3);
+            assert_eq!(archived.borrowed_name, "name");
+            assert_eq!(archived.owned_name, "name");
+            assert_eq!(archived.owned_tag, 3);
+            // Once per getter, including those that return a reference
+            assert_eq!(remote.calls.get(), 3);
+            // Cloned once per `getter_owned` field
+            assert_eq!(remote.clones.get(), 2);
+
+            let bytes = serialize::<Flags, _>(&remote::Flags(5));
+            let archived = archive::<Flags, remote::Flags>(&bytes);
+            assert_eq!(archived.0, 5);
+            assert_eq!(archived.1, !5);
+        }
+
+        #[test]
+        fn getter_value_names() {
+            struct Remote {
+                x: u32,
+                x_value: u64,
+            }
+
+            fn x(remote: &Remote) -> u32 {
+                remote.x * 2
+            }
+
+            // The getter's stored value must not collide with the field `x_value`
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(Remote))]
+            struct Example {
+                #[archive_with(getter = "x")]
+                x: u32,
+                x_value: u64,
+            }
+
+            let remote = Remote { x: 1, x_value: 3 };
+            let bytes = serialize::<Example, _>(&remote);
+            let archived = archive::<Example, Remote>(&bytes);
+            assert_eq!(archived.x, 2);
+            assert_eq!(archived.x_value, 3);
+        }
+
+        #[test]
+        fn getter_forms() {
+            mod remote {
+                use std::rc::Rc;
+
+                #[derive(Clone)]
+                pub struct Remote {
+                    pub(super) id: u64,
+                    name: String,
+                    bytes: Vec<u8>,
+                    tags: Vec<u32>,
+                    key: Rc<u64>,
+                }
+
+                impl Remote {
+                    pub fn new(id: u64, name: &str, bytes: &[u8], tags: &[u32]) -> Self {
+                        Self {
+                            id,
+                            name: name.to_owned(),
+                            bytes: bytes.to_owned(),
+                            tags: tags.to_owned(),
+                            key: Rc::new(id),
+                        }
+                    }
+
+                    pub fn inner(&self) -> Inner {
+                        Inner(self.id)
+                    }
+
+                    pub fn name(&self) -> &str {
+                        &self.name
+                    }
+
+                    pub fn bytes(&self) -> &[u8] {
+                        &self.bytes
+                    }
+
+                    pub fn tags(&self) -> &Vec<u32> {
+                        &self.tags
+                    }
+
+                    pub fn label(&self) -> &String {
+                        &self.name
+                    }
+
+                    pub fn key(&self) -> &Rc<u64> {
+                        &self.key
+                    }
+                }
+
+                pub struct Inner(u64);
+
+                impl Inner {
+                    pub fn id(&self) -> u64 {
+                        self.0
+                    }
+                }
+            }
+
+            #[derive(Archive, ArchiveWith)]
+            #[archive_with(rkyv = $version, from(remote::Remote))]
+            struct Example {
+                #[archive_with(getter = |remote: &remote::Remote| remote.inner().id())]
+                id: u64,
+                #[archive_with(getter = "|remote| remote.id * 2")]
+                double_id: u64,
+                #[archive_with(getter = |remote: remote::Remote| remote.id + 1, getter_owned)]
+                next_id: u64,
+                #[archive_with(getter = "remote::Remote::name", borrow(str))]
+                name: String,
+                #[archive_with(getter = "remote::Remote::bytes", borrow([u8]))]
+                bytes: Vec<u8>,
+                #[archive_with(getter = "remote::Remote::tags", borrow([u32]))]
+                tags: Vec<u32>,
+                #[archive_with(getter = "remote::Remote::label", borrow(str))]
+                label: String,
+                #[archive_with(getter = "remote::Remote::key", borrow)]
+                key: u64,
+            }
+
+            let remote = remote::Remote::new(3, "name", &[1, 2], &[4, 5, 6]);
+            let bytes = serialize::<Example, _>(&remote);
+            let archived = archive::<Example, remote::Remote>(&bytes);
+            assert_eq!(archived.id, 3);
+            assert_eq!(archived.double_id, 6);
+            assert_eq!(archived.next_id, 4);
+            assert_eq!(archived.name, "name");
+            assert_eq!(archived.bytes.as_slice(), [1, 2]);
+            assert_eq!(archived.tags.as_slice(), [4, 5, 6]);
+            assert_eq!(archived.label, "name");
+            assert_eq!(archived.key, 3);
+        }
+
+        #[test]
+        fn nested_path() {
+            // Without `Default` so that the levels are created through struct literals
+            #[derive(Debug, PartialEq)]
+            struct Remote {
+                name: String,
+                network: Network,
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct Network {
+                port: u16,
+                host: Host,
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct Host(String);
+
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote))]
+            struct Example {
+                name: String,
+                #[archive_with(path = "network.port", nested(Network))]
+                port: u16,
+                #[archive_with(path = "network.host.0", nested(Network, Host))]
+                host: String,
+            }
+
+            fn base() -> Remote {
+                Remote {
+                    name: "base".to_owned(),
+                    network: Network {
+                        port: 0,
+                        host: Host("base".to_owned()),
+                    },
+                }
+            }
+
+            // Assigned onto the base since it doesn't cover every level
+            #[derive(Archive, ArchiveWith, DeserializeWith)]
+            #[archive_with(rkyv = $version, from(Remote), default = "base")]
+            struct Port {
+                #[archive_with(path = "network.port")]
+                port: u16,
+            }
+
+            let remote = Remote {
+                name: "name".to_owned(),
+                network: Network {
+                    port: 8080,
+                    host: Host("localhost".to_owned()),
+                },
+            };
+
+            let bytes = serialize::<Example, _>(&remote);
+            let archived = archive::<Example, Remote>(&bytes);
+            assert_eq!(archived.port, 8080);
+            assert_eq!(archived.host, "localhost");
+
+            roundtrip::<Example, _>(&remote);
+
+            let mut expected = base();
+            expected.network.port = 8080;
+            assert_eq!(convert::<Port, _>(&remote), expected);
+        }
+    };
+}
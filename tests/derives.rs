@@ -1,17 +1,35 @@
-use std::{fmt::Debug, num::NonZeroU64, path::PathBuf};
+// Without `rkyv = "..."`, the derives target rkyv 0.8 if its feature is enabled
+#![cfg(not(feature = "rkyv-0_8"))]
+
+use std::{
+    fmt::Debug,
+    io,
+    marker::PhantomData,
+    net::Ipv4Addr,
+    num::{IntErrorKind, NonZeroU64, ParseIntError, TryFromIntError},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use rkyv::{
     ser::Serializer,
-    with::{ArchiveWith, AsString, CopyOptimize, DeserializeWith, Map, Niche, SerializeWith, With},
+    with::{
+        ArchiveWith, AsBox, AsString, CopyOptimize, DeserializeWith, Map, Niche, SerializeWith,
+        With,
+    },
     AlignedVec, Archive, Archived, Infallible,
 };
 use rkyv_with::{ArchiveWith, DeserializeWith};
-use serializer::CustomSerializer;
+use serializer::{CustomSerializer, CustomSerializerError, MissingValue, UnknownVariant};
 
 use crate::with_noop::WithNoop;
 
+#[macro_use]
+mod common;
+
 mod serializer {
-    use std::{alloc::Layout, ptr::NonNull};
+    use std::{alloc::Layout, num::ParseIntError, ptr::NonNull};
 
     use rkyv::{
         ser::{serializers::AllocSerializer, ScratchSpace, Serializer},
@@ -71,6 +89,9 @@ mod serializer {
     pub enum CustomSerializerError<E> {
         Inner(E),
         AsStringError(AsStringError),
+        UnknownVariant(UnknownVariant),
+        ParseIntError(ParseIntError),
+        MissingValue(MissingValue),
     }
 
     impl<E> From<AsStringError> for CustomSerializerError<E> {
@@ -78,6 +99,32 @@ mod serializer {
             Self::AsStringError(err)
         }
     }
+
+    impl<E> From<UnknownVariant> for CustomSerializerError<E> {
+        fn from(err: UnknownVariant) -> Self {
+            Self::UnknownVariant(err)
+        }
+    }
+
+    impl<E> From<ParseIntError> for CustomSerializerError<E> {
+        fn from(err: ParseIntError) -> Self {
+            Self::ParseIntError(err)
+        }
+    }
+
+    impl<E> From<MissingValue> for CustomSerializerError<E> {
+        fn from(err: MissingValue) -> Self {
+            Self::MissingValue(err)
+        }
+    }
+
+    /// Error for remote enum variants that are not covered by the mirror.
+    #[derive(Debug, Default)]
+    pub struct UnknownVariant;
+
+    /// Error for getters that return `None`.
+    #[derive(Debug, Default)]
+    pub struct MissingValue;
 }
 
 mod with_noop {
@@ -146,20 +193,30 @@ where
     unsafe { rkyv::archived_root::<With<Remote, Wrapper>>(bytes) }
 }
 
-fn roundtrip<Wrapper, Remote>(remote: &Remote)
+fn convert<Wrapper, Remote>(remote: &Remote) -> Remote
 where
     Wrapper: ArchiveWith<Remote>
         + SerializeWith<Remote, CustomSerializer<8>>
         + DeserializeWith<Archived<With<Remote, Wrapper>>, Remote, Infallible>,
-    Remote: Debug + PartialEq,
 {
     let bytes = serialize::<Wrapper, Remote>(remote);
     let archived = archive::<Wrapper, Remote>(&bytes);
-    let deserialized: Remote = Wrapper::deserialize_with(archived, &mut Infallible).unwrap();
 
-    assert_eq!(remote, &deserialized);
+    Wrapper::deserialize_with(archived, &mut Infallible).unwrap()
 }
 
+fn roundtrip<Wrapper, Remote>(remote: &Remote)
+where
+    Wrapper: ArchiveWith<Remote>
+        + SerializeWith<Remote, CustomSerializer<8>>
+        + DeserializeWith<Archived<With<Remote, Wrapper>>, Remote, Infallible>,
+    Remote: Debug + PartialEq,
+{
+    assert_eq!(remote, &convert::<Wrapper, Remote>(remote));
+}
+
+shared_tests!("0.7");
+
 #[test]
 fn named_struct() {
     #[derive(Debug, PartialEq)]
@@ -177,7 +234,6 @@ fn named_struct() {
         #[with(CopyOptimize)]
         b: Vec<A>,
         #[with(Niche)]
-        #[archive_with(from(Option<NonZeroU64>), via(Niche))]
         c: Option<NonZeroU64>,
         #[archive_with(from(Vec<PathBuf>), via(WithNoop, Map<AsString>))]
         d: Vec<String>,
@@ -203,9 +259,7 @@ fn unnamed_struct() {
     struct Example<A>(
         u8,
         #[with(CopyOptimize)] Vec<A>,
-        #[with(Niche)]
-        #[archive_with(from(Option<NonZeroU64>), via(Niche))]
-        Option<NonZeroU64>,
+        #[with(Niche)] Option<NonZeroU64>,
         #[archive_with(from(Vec<PathBuf>), via(WithNoop, Map<AsString>))] Vec<String>,
     );
 
@@ -213,19 +267,6 @@ fn unnamed_struct() {
     roundtrip::<Example<i32>, _>(&remote);
 }
 
-#[test]
-fn unit_struct() {
-    #[derive(Debug, PartialEq)]
-    struct Remote;
-
-    #[derive(Archive, ArchiveWith, DeserializeWith)]
-    #[archive_with(from(Remote))]
-    struct Example;
-
-    let remote = Remote;
-    roundtrip::<Example, _>(&remote);
-}
-
 #[test]
 fn full_enum() {
     #[derive(Debug, PartialEq)]
@@ -249,7 +290,6 @@ fn full_enum() {
             #[with(CopyOptimize)]
             a: Vec<A>,
             #[with(Niche)]
-            #[archive_with(from(Option<NonZeroU64>), via(Niche))]
             b: Option<NonZeroU64>,
             #[archive_with(from(Vec<PathBuf>), via(WithNoop, Map<AsString>))]
             c: Vec<String>,
@@ -270,83 +310,625 @@ fn full_enum() {
 }
 
 #[test]
-fn named_struct_private() {
+fn crate_path() {
+    mod facade {
+        pub use rkyv as reexported;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Remote {
+        a: u8,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive(crate = "facade::reexported")]
+    #[archive_with(from(Remote), crate = "facade::reexported")]
+    struct Example {
+        a: u8,
+    }
+
+    roundtrip::<Example, _>(&Remote { a: 1 });
+}
+
+#[test]
+fn other_variant() {
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(io::ErrorKind))]
+    enum ErrorKind {
+        NotFound,
+        PermissionDenied,
+        #[archive_with(other)]
+        Other,
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(io::ErrorKind), other_error = "UnknownVariant")]
+    enum ErrorKindStrict {
+        NotFound,
+        PermissionDenied,
+    }
+
+    roundtrip::<ErrorKind, _>(&io::ErrorKind::NotFound);
+    roundtrip::<ErrorKind, _>(&io::ErrorKind::Other);
+    roundtrip::<ErrorKindStrict, _>(&io::ErrorKind::PermissionDenied);
+
+    assert_eq!(
+        convert::<ErrorKind, _>(&io::ErrorKind::TimedOut),
+        io::ErrorKind::Other
+    );
+
+    let mut serializer = CustomSerializer::<8>::default();
+    let with = With::<_, ErrorKindStrict>::cast(&io::ErrorKind::TimedOut);
+    let res = serializer.serialize_value(with);
+
+    assert!(matches!(res, Err(CustomSerializerError::UnknownVariant(_))));
+}
+
+#[test]
+fn partial() {
+    #[derive(Debug, PartialEq)]
+    enum Remote {
+        A,
+        B(u8),
+        C { a: String },
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(Remote), partial, other_error = "UnknownVariant")]
+    enum Example {
+        A,
+        C { a: String },
+    }
+
+    roundtrip::<Example, _>(&Remote::A);
+    roundtrip::<Example, _>(&Remote::C { a: "a".to_owned() });
+
+    let mut serializer = CustomSerializer::<8>::default();
+    let with = With::<_, Example>::cast(&Remote::B(1));
+    let res = serializer.serialize_value(with);
+
+    assert!(matches!(res, Err(CustomSerializerError::UnknownVariant(_))));
+}
+
+#[test]
+fn recursive() {
+    #[derive(Debug, PartialEq)]
+    enum Expr {
+        Num(i32),
+        Call { name: String, args: Vec<Expr> },
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive(bound(serialize = "__S: rkyv::ser::ScratchSpace + rkyv::ser::Serializer"))]
+    #[archive_with(from(Expr))]
+    enum ExprMirror {
+        Num(i32),
+        Call {
+            name: String,
+            #[omit_bounds]
+            #[archive_with(
+                from(Vec<Expr>),
+                via(Map<ExprMirror>),
+                omit_bounds,
+                bound(serialize = "__S: rkyv::ser::ScratchSpace + rkyv::ser::Serializer")
+            )]
+            args: Vec<ExprMirror>,
+        },
+    }
+
+    let remote = Expr::Call {
+        name: "f".to_owned(),
+        args: vec![
+            Expr::Num(1),
+            Expr::Call {
+                name: "g".to_owned(),
+                args: vec![Expr::Num(2)],
+            },
+        ],
+    };
+
+    roundtrip::<ExprMirror, _>(&remote);
+}
+
+#[test]
+fn custom_names() {
+    #[derive(Debug, PartialEq)]
+    struct RemoteNamed {
+        a: u8,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive(archived = "CustomArchivedNamed", resolver = "CustomNamedResolver")]
+    #[archive_with(from(RemoteNamed))]
+    struct Named {
+        a: u8,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct RemoteUnnamed(u8, String);
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive(archived = "CustomArchivedUnnamed", resolver = "CustomUnnamedResolver")]
+    #[archive_with(from(RemoteUnnamed))]
+    struct Unnamed(u8, String);
+
+    #[derive(Debug, PartialEq)]
+    struct RemoteUnit;
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive(archived = "CustomArchivedUnit", resolver = "CustomUnitResolver")]
+    #[archive_with(from(RemoteUnit))]
+    struct Unit;
+
+    #[derive(Debug, PartialEq)]
+    enum RemoteEnum {
+        A,
+        B(u8),
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive(archived = "CustomArchivedEnum", resolver = "CustomEnumResolver")]
+    #[archive_with(from(RemoteEnum))]
+    enum Enum {
+        A,
+        B(u8),
+    }
+
+    roundtrip::<Named, _>(&RemoteNamed { a: 1 });
+    roundtrip::<Unnamed, _>(&RemoteUnnamed(1, "a".to_owned()));
+    roundtrip::<Unit, _>(&RemoteUnit);
+
+    for remote in [RemoteEnum::A, RemoteEnum::B(1)] {
+        roundtrip::<Enum, _>(&remote);
+    }
+}
+
+#[test]
+fn with_composition() {
+    #[derive(Debug, PartialEq)]
+    struct RemoteInner {
+        a: u8,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(RemoteInner))]
+    struct Inner {
+        a: u8,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Remote {
+        a: PathBuf,
+        b: RemoteInner,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(Remote))]
+    struct Example {
+        #[with(AsBox)]
+        #[archive_with(from(PathBuf), via(AsString))]
+        a: String,
+        #[with(AsBox)]
+        #[archive_with(from(RemoteInner))]
+        b: Inner,
+    }
+
+    let remote = Remote {
+        a: PathBuf::from("a"),
+        b: RemoteInner { a: 1 },
+    };
+
+    roundtrip::<Example, _>(&remote);
+}
+
+#[test]
+fn proxy() {
+    #[derive(Archive, rkyv::Serialize, rkyv::Deserialize, ArchiveWith, DeserializeWith)]
+    #[archive_with(
+        from(Duration),
+        into = "Seconds::from_duration",
+        from_mirror = "Seconds::into_duration"
+    )]
+    struct Seconds {
+        secs: u64,
+        nanos: u32,
+    }
+
+    static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+    impl Seconds {
+        fn from_duration(duration: &Duration) -> Self {
+            CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+
+            Self {
+                secs: duration.as_secs(),
+                nanos: duration.subsec_nanos(),
+            }
+        }
+
+        fn into_duration(self) -> Duration {
+            Duration::new(self.secs, self.nanos)
+        }
+    }
+
+    roundtrip::<Seconds, _>(&Duration::new(1, 2));
+    assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn via_conversions() {
+    // Converted by reference since it cannot be cloned
+    #[derive(Debug, PartialEq)]
+    struct Label(String);
+
+    impl From<&Label> for String {
+        fn from(label: &Label) -> Self {
+            label.0.clone()
+        }
+    }
+
+    impl From<String> for Label {
+        fn from(label: String) -> Self {
+            Self(label)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Remote {
+        ip: Ipv4Addr,
+        count: NonZeroU64,
+        label: Label,
+    }
+
+    #[derive(Archive, rkyv::Serialize, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(Remote))]
+    struct Example {
+        #[archive_with(from(Ipv4Addr), via_into(u32))]
+        ip: u32,
+        #[archive_with(from(NonZeroU64), via_try_from(u64))]
+        count: u64,
+        #[archive_with(from(Label), via_into(String))]
+        label: String,
+    }
+
+    struct TryDeserializer;
+
+    impl rkyv::Fallible for TryDeserializer {
+        type Error = TryFromIntError;
+    }
+
+    let remote = Remote {
+        ip: Ipv4Addr::LOCALHOST,
+        count: NonZeroU64::new(1).unwrap(),
+        label: Label("label".to_owned()),
+    };
+
+    let bytes = serialize::<Example, _>(&remote);
+    let archived = archive::<Example, Remote>(&bytes);
+    let deserialized: Remote = Example::deserialize_with(archived, &mut TryDeserializer).unwrap();
+    assert_eq!(remote, deserialized);
+
+    // Archived through the mirror's own impl to bypass the conversion
+    let mut serializer = CustomSerializer::<8>::default();
+    serializer
+        .serialize_value(&Example {
+            ip: 0,
+            count: 0,
+            label: String::new(),
+        })
+        .unwrap();
+    let bytes = serializer.into_bytes();
+    let archived = unsafe { rkyv::archived_root::<Example>(&bytes) };
+
+    let res: Result<Remote, _> = Example::deserialize_with(archived, &mut TryDeserializer);
+    assert!(res.is_err());
+}
+
+#[test]
+fn fallible_getter() {
     mod remote {
-        #[derive(Copy, Clone, Default)]
+        use std::num::ParseIntError;
+
         pub struct Remote {
-            inner: [u8; 4],
+            raw: String,
+            missing: bool,
         }
 
         impl Remote {
-            pub fn into_inner(self) -> [u8; 4] {
-                self.inner
+            pub fn new(raw: &str, missing: bool) -> Self {
+                Self {
+                    raw: raw.to_owned(),
+                    missing,
+                }
             }
 
-            pub fn to_inner(&self) -> [u8; 4] {
-                self.inner
+            pub fn parsed(&self) -> Result<u32, ParseIntError> {
+                self.raw.parse()
             }
 
-            pub fn as_inner(&self) -> &[u8; 4] {
-                &self.inner
+            pub fn optional(&self) -> Option<u8> {
+                (!self.missing).then_some(1)
             }
         }
     }
 
     #[derive(Archive, ArchiveWith)]
     #[archive_with(from(remote::Remote))]
-    struct ExampleByVal {
-        #[archive_with(getter = "remote::Remote::into_inner", getter_owned)]
-        inner: [u8; 4],
+    struct Example {
+        #[archive_with(getter = "remote::Remote::parsed", fallible = "ParseIntError")]
+        parsed: u32,
+        // `None` is turned into the error's `Default` value
+        #[archive_with(getter = "remote::Remote::optional", fallible = "MissingValue")]
+        optional: u8,
     }
 
-    #[derive(Archive, ArchiveWith)]
-    #[archive_with(from(remote::Remote))]
-    struct ExampleByRef {
-        #[archive_with(getter = "remote::Remote::to_inner")]
-        inner: [u8; 4],
+    let bytes = serialize::<Example, _>(&remote::Remote::new("12", false));
+    let archived = archive::<Example, remote::Remote>(&bytes);
+    assert_eq!(archived.parsed, 12);
+    assert_eq!(archived.optional, 1);
+
+    let mut serializer = CustomSerializer::<8>::default();
+    let remote = remote::Remote::new("a", false);
+    let with = With::<_, Example>::cast(&remote);
+
+    match serializer.serialize_value(with) {
+        Err(CustomSerializerError::ParseIntError(err)) => {
+            assert_eq!(err.kind(), &IntErrorKind::InvalidDigit)
+        }
+        _ => panic!("expected `ParseIntError`"),
     }
 
-    #[derive(Archive, ArchiveWith)]
+    let mut serializer = CustomSerializer::<8>::default();
+    let remote = remote::Remote::new("12", true);
+    let with = With::<_, Example>::cast(&remote);
+    let res = serializer.serialize_value(with);
+
+    assert!(matches!(res, Err(CustomSerializerError::MissingValue(_))));
+}
+
+#[test]
+fn borrowed_remote() {
+    use rkyv::with::RefAsBox;
+
+    #[derive(Debug, PartialEq)]
+    struct Remote<'a> {
+        name: &'a str,
+        bytes: &'a [u8],
+        id: u32,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(Remote<'a>))]
+    struct Example<'a> {
+        #[with(RefAsBox)]
+        name: &'a str,
+        #[with(RefAsBox)]
+        bytes: &'a [u8],
+        id: u32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum RemoteEnum<'a> {
+        Name(&'a str),
+        Id(u32),
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(RemoteEnum<'a>))]
+    enum ExampleEnum<'a> {
+        Name(#[with(RefAsBox)] &'a str),
+        Id(u32),
+    }
+
+    let remote = Remote {
+        name: "name",
+        bytes: &[1, 2, 3],
+        id: 4,
+    };
+
+    let bytes = serialize::<Example, _>(&remote);
+    let archived = archive::<Example, Remote>(&bytes);
+    let borrowed = Example::from_archived(archived, &mut Infallible).unwrap();
+    assert_eq!(borrowed, remote);
+
+    // The borrowed fields point into the archive
+    assert!(bytes.as_ptr_range().contains(&borrowed.name.as_ptr()));
+    assert!(bytes.as_ptr_range().contains(&borrowed.bytes.as_ptr()));
+
+    for remote in [RemoteEnum::Name("name"), RemoteEnum::Id(4)] {
+        let bytes = serialize::<ExampleEnum, _>(&remote);
+        let archived = archive::<ExampleEnum, RemoteEnum>(&bytes);
+        let borrowed = ExampleEnum::from_archived(archived, &mut Infallible).unwrap();
+        assert_eq!(borrowed, remote);
+    }
+}
+
+/// Only the derive macros are used, none of rkyv's traits are imported
+mod without_imports {
+    use std::time::Duration;
+
+    pub mod remote {
+        #[derive(Debug, PartialEq)]
+        pub struct Remote {
+            pub id: u32,
+            pub name: String,
+            pub bytes: Vec<u8>,
+        }
+
+        impl Remote {
+            pub fn name(&self) -> &str {
+                &self.name
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub enum RemoteEnum {
+            A(u32),
+            B { inner: RemoteInner },
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub struct RemoteInner(pub u8);
+    }
+
+    #[derive(rkyv::Archive, rkyv_with::ArchiveWith, rkyv_with::DeserializeWith)]
     #[archive_with(from(remote::Remote))]
-    struct ExampleThroughRef {
-        #[archive_with(getter = "remote::Remote::as_inner")]
-        inner: [u8; 4],
+    pub struct Example {
+        id: u32,
+        #[archive_with(getter = "remote::Remote::name", borrow(str))]
+        name: String,
+        #[with(rkyv::with::CopyOptimize)]
+        bytes: Vec<u8>,
     }
 
-    let remote = remote::Remote::default();
-    let _ = archive::<ExampleByVal, _>(&serialize::<ExampleByVal, _>(&remote));
-    let _ = archive::<ExampleByRef, _>(&serialize::<ExampleByRef, _>(&remote));
-    let _ = archive::<ExampleThroughRef, _>(&serialize::<ExampleThroughRef, _>(&remote));
+    #[allow(unused)]
+    #[derive(rkyv::Archive, rkyv_with::ArchiveWith, rkyv_with::DeserializeWith)]
+    #[archive_with(from(remote::RemoteEnum))]
+    pub enum ExampleEnum {
+        A(u32),
+        B {
+            #[archive_with(from(remote::RemoteInner), via(Inner))]
+            inner: Inner,
+        },
+    }
+
+    #[derive(rkyv::Archive, rkyv_with::ArchiveWith, rkyv_with::DeserializeWith)]
+    #[archive_with(from(remote::RemoteInner))]
+    pub struct Inner(u8);
+
+    #[derive(
+        rkyv::Archive,
+        rkyv::Serialize,
+        rkyv::Deserialize,
+        rkyv_with::ArchiveWith,
+        rkyv_with::DeserializeWith,
+    )]
+    #[archive_with(
+        from(Duration),
+        into = "Seconds::from_duration",
+        from_mirror = "Seconds::into_duration"
+    )]
+    pub struct Seconds(u64);
+
+    impl Seconds {
+        fn from_duration(duration: &Duration) -> Self {
+            Self(duration.as_secs())
+        }
+
+        fn into_duration(self) -> Duration {
+            Duration::from_secs(self.0)
+        }
+    }
 }
 
 #[test]
-fn unnamed_struct_private() {
-    mod remote {
-        #[derive(Copy, Clone, Default)]
-        pub struct Remote([u8; 4]);
+fn without_imports() {
+    use without_imports::remote::{Remote, RemoteEnum, RemoteInner};
+
+    let remote = Remote {
+        id: 1,
+        name: "name".to_owned(),
+        bytes: vec![2, 3],
+    };
+
+    roundtrip::<without_imports::Example, _>(&remote);
+
+    let remote = RemoteEnum::B {
+        inner: RemoteInner(4),
+    };
+
+    roundtrip::<without_imports::ExampleEnum, _>(&remote);
+    roundtrip::<without_imports::Seconds, _>(&Duration::from_secs(5));
+}
+
+/// Mirrors that are part of the public interface, unlike those within tests
+pub mod public {
+    use std::net::Ipv4Addr;
+    use std::num::ParseIntError;
+
+    use rkyv::Archive;
+    use rkyv_with::{ArchiveWith, DeserializeWith};
+
+    pub mod remote {
+        use std::{net::Ipv4Addr, num::ParseIntError};
+
+        pub struct Remote {
+            raw: String,
+        }
 
         impl Remote {
-            pub fn into_inner(self) -> [u8; 4] {
-                self.0
+            pub fn new(raw: &str) -> Self {
+                Self {
+                    raw: raw.to_owned(),
+                }
+            }
+
+            pub fn parsed(&self) -> Result<u32, ParseIntError> {
+                self.raw.parse()
             }
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct UserId(u64);
 
-            pub fn as_inner(&self) -> [u8; 4] {
-                self.0
+        impl From<u64> for UserId {
+            fn from(id: u64) -> Self {
+                Self(id)
             }
         }
+
+        impl From<UserId> for u64 {
+            fn from(id: UserId) -> Self {
+                id.0
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub struct Address {
+            pub ip: Ipv4Addr,
+        }
     }
 
     #[derive(Archive, ArchiveWith)]
     #[archive_with(from(remote::Remote))]
-    struct ExampleByRef(#[archive_with(getter = "remote::Remote::as_inner")] [u8; 4]);
+    pub struct FallibleGetter {
+        #[archive_with(getter = "remote::Remote::parsed", fallible = "ParseIntError")]
+        pub parsed: u32,
+    }
 
-    #[derive(Archive, ArchiveWith)]
-    #[archive_with(from(remote::Remote))]
-    struct ExampleByVal(
-        #[archive_with(getter = "remote::Remote::into_inner", getter_owned)] [u8; 4],
-    );
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(remote::UserId), transparent)]
+    pub struct Transparent(pub u64);
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(from(remote::Address))]
+    pub struct ViaInto {
+        #[archive_with(from(Ipv4Addr), via_into(u32))]
+        pub ip: u32,
+    }
+}
+
+#[test]
+fn public_mirrors() {
+    use public::remote::{Address, Remote, UserId};
+
+    let remote = Remote::new("12");
+
+    let bytes = serialize::<public::FallibleGetter, _>(&remote);
+    let archived = archive::<public::FallibleGetter, Remote>(&bytes);
+    assert_eq!(archived.parsed, 12);
+
+    roundtrip::<public::Transparent, _>(&UserId::from(1));
+
+    let address = Address {
+        ip: Ipv4Addr::LOCALHOST,
+    };
 
-    let remote = remote::Remote::default();
-    let _ = archive::<ExampleByRef, _>(&serialize::<ExampleByRef, _>(&remote));
-    let _ = archive::<ExampleByVal, _>(&serialize::<ExampleByVal, _>(&remote));
+    roundtrip::<public::ViaInto, _>(&address);
 }
@@ -0,0 +1,863 @@
+extern crate rkyv_0_8 as rkyv;
+
+use std::{
+    fmt::Debug,
+    io,
+    marker::PhantomData,
+    net::Ipv4Addr,
+    num::{NonZeroU64, TryFromIntError},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use rkyv::{
+    api::high::{HighDeserializer, HighSerializer},
+    de::Pool,
+    rancor::{Error, Strategy},
+    ser::allocator::ArenaHandle,
+    util::AlignedVec,
+    with::{ArchiveWith, AsBox, AsString, DeserializeWith, Map, Niche, SerializeWith, With},
+    Archive,
+};
+use rkyv_with::{ArchiveWith, DeserializeWith};
+
+use crate::with_noop::WithNoop;
+
+#[macro_use]
+mod common;
+
+mod with_noop {
+    use rkyv::{
+        rancor::Fallible,
+        with::{ArchiveWith, DeserializeWith, SerializeWith},
+        Archive, Archived, Deserialize, Place, Serialize,
+    };
+
+    /// Usable as rkyv With-wrapper which doesn't to anything
+    /// and just uses the type's Archive/Deserialize/Serialize impls.
+    pub struct WithNoop;
+
+    impl<F: Archive> ArchiveWith<F> for WithNoop {
+        type Archived = <F as Archive>::Archived;
+        type Resolver = <F as Archive>::Resolver;
+
+        fn resolve_with(field: &F, resolver: Self::Resolver, out: Place<Self::Archived>) {
+            field.resolve(resolver, out)
+        }
+    }
+
+    impl<F: Serialize<S>, S: Fallible + ?Sized> SerializeWith<F, S> for WithNoop {
+        fn serialize_with(
+            field: &F,
+            serializer: &mut S,
+        ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+            field.serialize(serializer)
+        }
+    }
+
+    impl<F, D> DeserializeWith<Archived<F>, F, D> for WithNoop
+    where
+        F: Archive,
+        Archived<F>: Deserialize<F, D>,
+        D: Fallible + ?Sized,
+    {
+        fn deserialize_with(
+            field: &Archived<F>,
+            deserializer: &mut D,
+        ) -> Result<F, <D as Fallible>::Error> {
+            field.deserialize(deserializer)
+        }
+    }
+}
+
+fn serialize<Wrapper, Remote>(remote: &Remote) -> AlignedVec
+where
+    Wrapper: for<'a> SerializeWith<Remote, HighSerializer<AlignedVec, ArenaHandle<'a>, Error>>,
+{
+    let with = With::<Remote, Wrapper>::cast(remote);
+
+    rkyv::to_bytes::<Error>(with).unwrap()
+}
+
+fn archive<Wrapper, Remote>(bytes: &[u8]) -> &<Wrapper as ArchiveWith<Remote>>::Archived
+where
+    Wrapper: ArchiveWith<Remote>,
+{
+    unsafe { rkyv::access_unchecked::<<Wrapper as ArchiveWith<Remote>>::Archived>(bytes) }
+}
+
+fn convert<Wrapper, Remote>(remote: &Remote) -> Remote
+where
+    Wrapper: for<'a> SerializeWith<Remote, HighSerializer<AlignedVec, ArenaHandle<'a>, Error>>
+        + DeserializeWith<<Wrapper as ArchiveWith<Remote>>::Archived, Remote, HighDeserializer<Error>>,
+{
+    let bytes = serialize::<Wrapper, Remote>(remote);
+    let archived = archive::<Wrapper, Remote>(&bytes);
+    let mut pool = Pool::new();
+    let deserializer = Strategy::<_, Error>::wrap(&mut pool);
+
+    Wrapper::deserialize_with(archived, deserializer).unwrap()
+}
+
+fn roundtrip<Wrapper, Remote>(remote: &Remote)
+where
+    Wrapper: for<'a> SerializeWith<Remote, HighSerializer<AlignedVec, ArenaHandle<'a>, Error>>
+        + DeserializeWith<<Wrapper as ArchiveWith<Remote>>::Archived, Remote, HighDeserializer<Error>>,
+    Remote: Debug + PartialEq,
+{
+    assert_eq!(remote, &convert::<Wrapper, Remote>(remote));
+}
+
+shared_tests!("0.8");
+
+#[test]
+fn named_struct() {
+    #[derive(Debug, PartialEq)]
+    struct Remote<A> {
+        a: u8,
+        b: Vec<A>,
+        c: Option<NonZeroU64>,
+        d: Vec<PathBuf>,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(Remote::<A>))]
+    struct Example<A> {
+        a: u8,
+        #[rkyv(with = WithNoop)]
+        b: Vec<A>,
+        #[rkyv(with = Niche)]
+        c: Option<NonZeroU64>,
+        #[archive_with(from(Vec<PathBuf>), via(Map<AsString>))]
+        d: Vec<String>,
+    }
+
+    let remote = Remote {
+        a: 0,
+        b: vec![1, 2],
+        c: NonZeroU64::new(3),
+        d: vec![PathBuf::from("a")],
+    };
+
+    roundtrip::<Example<i32>, _>(&remote);
+}
+
+#[test]
+fn unnamed_struct() {
+    #[derive(Debug, PartialEq)]
+    struct Remote<A>(u8, Vec<A>, Option<NonZeroU64>, Vec<PathBuf>);
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(Remote::<A>))]
+    struct Example<A>(
+        u8,
+        #[rkyv(with = WithNoop)] Vec<A>,
+        #[rkyv(with = Niche)] Option<NonZeroU64>,
+        #[archive_with(from(Vec<PathBuf>), via(Map<AsString>))] Vec<String>,
+    );
+
+    let remote = Remote(0, vec![1, 2], NonZeroU64::new(3), vec![PathBuf::from("a")]);
+    roundtrip::<Example<i32>, _>(&remote);
+}
+
+#[test]
+fn full_enum() {
+    #[derive(Debug, PartialEq)]
+    enum Remote<A> {
+        A,
+        B(u8),
+        C {
+            a: Vec<A>,
+            b: Option<NonZeroU64>,
+            c: Vec<PathBuf>,
+        },
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(Remote::<A>))]
+    enum Example<A> {
+        A,
+        B(u8),
+        C {
+            #[rkyv(with = WithNoop)]
+            a: Vec<A>,
+            #[rkyv(with = Niche)]
+            b: Option<NonZeroU64>,
+            #[archive_with(from(Vec<PathBuf>), via(Map<AsString>))]
+            c: Vec<String>,
+        },
+    }
+
+    for remote in [
+        Remote::A,
+        Remote::B(0),
+        Remote::C {
+            a: vec![1, 2],
+            b: NonZeroU64::new(3),
+            c: vec![PathBuf::from("a")],
+        },
+    ] {
+        roundtrip::<Example<i32>, _>(&remote);
+    }
+}
+
+#[test]
+fn crate_path() {
+    mod facade {
+        pub use rkyv_0_8 as reexported;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Remote {
+        a: u8,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[rkyv(crate = facade::reexported)]
+    #[archive_with(rkyv = "0.8", from(Remote), crate = facade::reexported)]
+    struct Example {
+        a: u8,
+    }
+
+    roundtrip::<Example, _>(&Remote { a: 1 });
+}
+
+#[test]
+fn other_variant() {
+    #[derive(Debug, Default)]
+    struct UnknownVariant;
+
+    impl std::fmt::Display for UnknownVariant {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("unknown variant")
+        }
+    }
+
+    impl std::error::Error for UnknownVariant {}
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(io::ErrorKind))]
+    enum ErrorKind {
+        NotFound,
+        PermissionDenied,
+        #[archive_with(other)]
+        Other,
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(io::ErrorKind), other_error = "UnknownVariant")]
+    enum ErrorKindStrict {
+        NotFound,
+        PermissionDenied,
+    }
+
+    roundtrip::<ErrorKind, _>(&io::ErrorKind::NotFound);
+    roundtrip::<ErrorKind, _>(&io::ErrorKind::Other);
+    roundtrip::<ErrorKindStrict, _>(&io::ErrorKind::PermissionDenied);
+
+    assert_eq!(
+        convert::<ErrorKind, _>(&io::ErrorKind::TimedOut),
+        io::ErrorKind::Other
+    );
+
+    let with = With::<_, ErrorKindStrict>::cast(&io::ErrorKind::TimedOut);
+    let err = rkyv::to_bytes::<Error>(with).unwrap_err();
+
+    assert_eq!(err.to_string(), "unknown variant");
+}
+
+#[test]
+fn partial() {
+    #[derive(Debug, PartialEq)]
+    enum Remote {
+        A,
+        B(u8),
+        C { a: String },
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(Remote), partial)]
+    enum Example {
+        A,
+        C { a: String },
+    }
+
+    roundtrip::<Example, _>(&Remote::A);
+    roundtrip::<Example, _>(&Remote::C { a: "a".to_owned() });
+
+    let with = With::<_, Example>::cast(&Remote::B(1));
+    let err = rkyv::to_bytes::<Error>(with).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "variant of the remote type is not mapped by `Example`"
+    );
+}
+
+#[test]
+fn recursive() {
+    #[derive(Debug, PartialEq)]
+    enum Expr {
+        Num(i32),
+        Call { name: String, args: Vec<Expr> },
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[rkyv(serialize_bounds(
+        __S: rkyv::ser::Writer + rkyv::ser::Allocator,
+        __S::Error: rkyv::rancor::Source,
+    ))]
+    #[rkyv(deserialize_bounds(__D::Error: rkyv::rancor::Source))]
+    #[rkyv(bytecheck(bounds(
+        __C: rkyv::validation::ArchiveContext,
+        __C::Error: rkyv::rancor::Source,
+    )))]
+    #[archive_with(rkyv = "0.8", from(Expr))]
+    enum ExprMirror {
+        Num(i32),
+        Call {
+            name: String,
+            #[rkyv(omit_bounds)]
+            #[archive_with(
+                from(Vec<Expr>),
+                via(Map<ExprMirror>),
+                omit_bounds,
+                bound(
+                    serialize = "__S: rkyv::ser::Writer + rkyv::ser::Allocator, \
+                        __S::Error: rkyv::rancor::Source",
+                    deserialize = "__D::Error: rkyv::rancor::Source",
+                )
+            )]
+            args: Vec<ExprMirror>,
+        },
+    }
+
+    let remote = Expr::Call {
+        name: "f".to_owned(),
+        args: vec![
+            Expr::Num(1),
+            Expr::Call {
+                name: "g".to_owned(),
+                args: vec![Expr::Num(2)],
+            },
+        ],
+    };
+
+    roundtrip::<ExprMirror, _>(&remote);
+}
+
+#[test]
+fn custom_names() {
+    #[derive(Debug, PartialEq)]
+    struct RemoteNamed {
+        a: u8,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[rkyv(archived = CustomArchivedNamed, resolver = CustomNamedResolver)]
+    #[archive_with(rkyv = "0.8", from(RemoteNamed))]
+    struct Named {
+        a: u8,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct RemoteUnnamed(u8, String);
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[rkyv(archived = CustomArchivedUnnamed, resolver = CustomUnnamedResolver)]
+    #[archive_with(rkyv = "0.8", from(RemoteUnnamed))]
+    struct Unnamed(u8, String);
+
+    #[derive(Debug, PartialEq)]
+    struct RemoteUnit;
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[rkyv(archived = CustomArchivedUnit, resolver = CustomUnitResolver)]
+    #[archive_with(rkyv = "0.8", from(RemoteUnit))]
+    struct Unit;
+
+    #[derive(Debug, PartialEq)]
+    enum RemoteEnum {
+        A,
+        B(u8),
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[rkyv(archived = CustomArchivedEnum, resolver = CustomEnumResolver)]
+    #[archive_with(rkyv = "0.8", from(RemoteEnum))]
+    enum Enum {
+        A,
+        B(u8),
+    }
+
+    roundtrip::<Named, _>(&RemoteNamed { a: 1 });
+    roundtrip::<Unnamed, _>(&RemoteUnnamed(1, "a".to_owned()));
+    roundtrip::<Unit, _>(&RemoteUnit);
+
+    for remote in [RemoteEnum::A, RemoteEnum::B(1)] {
+        roundtrip::<Enum, _>(&remote);
+    }
+}
+
+#[test]
+fn with_composition() {
+    struct RemoteInner {
+        a: u8,
+    }
+
+    #[derive(Archive, ArchiveWith)]
+    #[archive_with(rkyv = "0.8", from(RemoteInner))]
+    struct Inner {
+        a: u8,
+    }
+
+    struct Remote {
+        a: PathBuf,
+        b: RemoteInner,
+    }
+
+    // rkyv 0.8 cannot deserialize through the nested wrappers
+    #[derive(Archive, ArchiveWith)]
+    #[archive_with(rkyv = "0.8", from(Remote))]
+    struct Example {
+        #[rkyv(with = AsBox)]
+        #[archive_with(from(PathBuf), via(AsString))]
+        a: String,
+        #[rkyv(with = AsBox)]
+        #[archive_with(from(RemoteInner))]
+        b: Inner,
+    }
+
+    let remote = Remote {
+        a: PathBuf::from("a"),
+        b: RemoteInner { a: 1 },
+    };
+
+    let bytes = serialize::<Example, _>(&remote);
+    let archived = archive::<Example, Remote>(&bytes);
+
+    assert_eq!(archived.a.as_str(), "a");
+    assert_eq!(archived.b.a, 1);
+}
+
+#[test]
+fn proxy() {
+    #[derive(Archive, rkyv::Serialize, rkyv::Deserialize, ArchiveWith, DeserializeWith)]
+    #[archive_with(
+        rkyv = "0.8",
+        from(Duration),
+        into = "Seconds::from_duration",
+        from_mirror = "Seconds::into_duration"
+    )]
+    struct Seconds {
+        secs: u64,
+        nanos: u32,
+    }
+
+    static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+    impl Seconds {
+        fn from_duration(duration: &Duration) -> Self {
+            CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+
+            Self {
+                secs: duration.as_secs(),
+                nanos: duration.subsec_nanos(),
+            }
+        }
+
+        fn into_duration(self) -> Duration {
+            Duration::new(self.secs, self.nanos)
+        }
+    }
+
+    #[derive(Archive, rkyv::Serialize, rkyv::Deserialize, ArchiveWith, DeserializeWith)]
+    #[archive_with(
+        rkyv = "0.8",
+        from(NonZeroU64),
+        into = "Count::from_remote",
+        try_from_mirror = "Count::try_into_remote"
+    )]
+    struct Count(u64);
+
+    impl Count {
+        fn from_remote(count: &NonZeroU64) -> Self {
+            Self(count.get())
+        }
+
+        fn try_into_remote(self) -> Result<NonZeroU64, TryFromIntError> {
+            NonZeroU64::try_from(self.0)
+        }
+    }
+
+    roundtrip::<Seconds, _>(&Duration::new(1, 2));
+    assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 1);
+    roundtrip::<Count, _>(&NonZeroU64::new(1).unwrap());
+
+    // Archived through the mirror's own impl to bypass the conversion
+    let bytes = rkyv::to_bytes::<Error>(&Count(0)).unwrap();
+    let archived = unsafe { rkyv::access_unchecked::<ArchivedCount>(&bytes) };
+    let mut pool = Pool::new();
+    let deserializer = Strategy::<_, Error>::wrap(&mut pool);
+
+    let res: Result<NonZeroU64, Error> = Count::deserialize_with(archived, deserializer);
+    assert!(res.is_err());
+}
+
+#[test]
+fn via_conversions() {
+    // Converted by reference since it cannot be cloned
+    #[derive(Debug, PartialEq)]
+    struct Label(String);
+
+    impl From<&Label> for String {
+        fn from(label: &Label) -> Self {
+            label.0.clone()
+        }
+    }
+
+    impl From<String> for Label {
+        fn from(label: String) -> Self {
+            Self(label)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Remote {
+        ip: Ipv4Addr,
+        count: NonZeroU64,
+        label: Label,
+    }
+
+    #[derive(Archive, rkyv::Serialize, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(Remote))]
+    struct Example {
+        #[archive_with(from(Ipv4Addr), via_into(u32))]
+        ip: u32,
+        #[archive_with(from(NonZeroU64), via_try_from(u64))]
+        count: u64,
+        #[archive_with(from(Label), via_into(String))]
+        label: String,
+    }
+
+    roundtrip::<Example, _>(&Remote {
+        ip: Ipv4Addr::LOCALHOST,
+        count: NonZeroU64::new(1).unwrap(),
+        label: Label("label".to_owned()),
+    });
+
+    // Archived through the mirror's own impl to bypass the conversion
+    let bytes = rkyv::to_bytes::<Error>(&Example {
+        ip: 0,
+        count: 0,
+        label: String::new(),
+    })
+    .unwrap();
+    let archived = unsafe { rkyv::access_unchecked::<ArchivedExample>(&bytes) };
+    let mut pool = Pool::new();
+    let deserializer = Strategy::<_, Error>::wrap(&mut pool);
+
+    let res: Result<Remote, Error> = Example::deserialize_with(archived, deserializer);
+    assert!(res.is_err());
+}
+
+#[test]
+fn fallible_getter() {
+    mod remote {
+        use std::num::ParseIntError;
+
+        pub struct Remote {
+            raw: String,
+            missing: bool,
+        }
+
+        impl Remote {
+            pub fn new(raw: &str, missing: bool) -> Self {
+                Self {
+                    raw: raw.to_owned(),
+                    missing,
+                }
+            }
+
+            pub fn parsed(&self) -> Result<u32, ParseIntError> {
+                self.raw.parse()
+            }
+
+            pub fn optional(&self) -> Option<u8> {
+                (!self.missing).then_some(1)
+            }
+        }
+    }
+
+    #[derive(Archive, ArchiveWith)]
+    #[archive_with(rkyv = "0.8", from(remote::Remote))]
+    struct Example {
+        #[archive_with(getter = "remote::Remote::parsed", fallible)]
+        parsed: u32,
+        #[archive_with(getter = "remote::Remote::optional", fallible)]
+        optional: u8,
+    }
+
+    let bytes = serialize::<Example, _>(&remote::Remote::new("12", false));
+    let archived = archive::<Example, remote::Remote>(&bytes);
+    assert_eq!(archived.parsed, 12);
+    assert_eq!(archived.optional, 1);
+
+    for remote in [
+        remote::Remote::new("a", false),
+        remote::Remote::new("12", true),
+    ] {
+        let with = With::<_, Example>::cast(&remote);
+        assert!(rkyv::to_bytes::<Error>(with).is_err());
+    }
+}
+
+#[test]
+fn borrowed_remote() {
+    use rkyv::with::AsVec;
+
+    #[derive(Debug, PartialEq)]
+    struct Remote<'a> {
+        name: &'a str,
+        bytes: &'a [u8],
+        id: u32,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(Remote<'a>))]
+    struct Example<'a> {
+        #[rkyv(with = AsString)]
+        name: &'a str,
+        #[rkyv(with = AsVec)]
+        bytes: &'a [u8],
+        id: u32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum RemoteEnum<'a> {
+        Name(&'a str),
+        Id(u32),
+    }
+
+    #[allow(unused)]
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(RemoteEnum<'a>))]
+    enum ExampleEnum<'a> {
+        Name(#[rkyv(with = AsString)] &'a str),
+        Id(u32),
+    }
+
+    let remote = Remote {
+        name: "name",
+        bytes: &[1, 2, 3],
+        id: 4,
+    };
+
+    let bytes = serialize::<Example, _>(&remote);
+    let archived = archive::<Example, Remote>(&bytes);
+    let mut pool = Pool::new();
+    let deserializer = Strategy::<_, Error>::wrap(&mut pool);
+    let borrowed = Example::from_archived(archived, deserializer).unwrap();
+    assert_eq!(borrowed, remote);
+
+    // The borrowed fields point into the archive
+    assert!(bytes.as_ptr_range().contains(&borrowed.name.as_ptr()));
+    assert!(bytes.as_ptr_range().contains(&borrowed.bytes.as_ptr()));
+
+    for remote in [RemoteEnum::Name("name"), RemoteEnum::Id(4)] {
+        let bytes = serialize::<ExampleEnum, _>(&remote);
+        let archived = archive::<ExampleEnum, RemoteEnum>(&bytes);
+        let borrowed = ExampleEnum::from_archived(archived, deserializer).unwrap();
+        assert_eq!(borrowed, remote);
+    }
+}
+
+/// Only the derive macros are used, none of rkyv's traits are imported
+mod without_imports {
+    use std::time::Duration;
+
+    pub mod remote {
+        #[derive(Debug, PartialEq)]
+        pub struct Remote {
+            pub id: u32,
+            pub name: String,
+            pub bytes: Vec<u8>,
+        }
+
+        impl Remote {
+            pub fn name(&self) -> &str {
+                &self.name
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub enum RemoteEnum {
+            A(u32),
+            B { inner: RemoteInner },
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub struct RemoteInner(pub u8);
+    }
+
+    #[derive(rkyv::Archive, rkyv_with::ArchiveWith, rkyv_with::DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(remote::Remote))]
+    pub struct Example {
+        id: u32,
+        #[archive_with(getter = "remote::Remote::name", borrow(str))]
+        name: String,
+        #[rkyv(with = rkyv::with::AsBox)]
+        bytes: Vec<u8>,
+    }
+
+    #[allow(unused)]
+    #[derive(rkyv::Archive, rkyv_with::ArchiveWith, rkyv_with::DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(remote::RemoteEnum))]
+    pub enum ExampleEnum {
+        A(u32),
+        B {
+            #[archive_with(from(remote::RemoteInner), via(Inner))]
+            inner: Inner,
+        },
+    }
+
+    #[derive(rkyv::Archive, rkyv_with::ArchiveWith, rkyv_with::DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(remote::RemoteInner))]
+    pub struct Inner(u8);
+
+    #[derive(
+        rkyv::Archive,
+        rkyv::Serialize,
+        rkyv::Deserialize,
+        rkyv_with::ArchiveWith,
+        rkyv_with::DeserializeWith,
+    )]
+    #[archive_with(
+        rkyv = "0.8",
+        from(Duration),
+        into = "Seconds::from_duration",
+        from_mirror = "Seconds::into_duration"
+    )]
+    pub struct Seconds(u64);
+
+    impl Seconds {
+        fn from_duration(duration: &Duration) -> Self {
+            Self(duration.as_secs())
+        }
+
+        fn into_duration(self) -> Duration {
+            Duration::from_secs(self.0)
+        }
+    }
+}
+
+#[test]
+fn without_imports() {
+    use without_imports::remote::{Remote, RemoteEnum, RemoteInner};
+
+    let remote = Remote {
+        id: 1,
+        name: "name".to_owned(),
+        bytes: vec![2, 3],
+    };
+
+    roundtrip::<without_imports::Example, _>(&remote);
+
+    let remote = RemoteEnum::B {
+        inner: RemoteInner(4),
+    };
+
+    roundtrip::<without_imports::ExampleEnum, _>(&remote);
+    roundtrip::<without_imports::Seconds, _>(&Duration::from_secs(5));
+}
+
+/// Mirrors that are part of the public interface, unlike those within tests
+pub mod public {
+    use rkyv::Archive;
+    use rkyv_with::{ArchiveWith, DeserializeWith};
+    use std::net::Ipv4Addr;
+
+    pub mod remote {
+        use std::{net::Ipv4Addr, num::ParseIntError};
+
+        pub struct Remote {
+            raw: String,
+        }
+
+        impl Remote {
+            pub fn new(raw: &str) -> Self {
+                Self {
+                    raw: raw.to_owned(),
+                }
+            }
+
+            pub fn parsed(&self) -> Result<u32, ParseIntError> {
+                self.raw.parse()
+            }
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct UserId(u64);
+
+        impl From<u64> for UserId {
+            fn from(id: u64) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<UserId> for u64 {
+            fn from(id: UserId) -> Self {
+                id.0
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub struct Address {
+            pub ip: Ipv4Addr,
+        }
+    }
+
+    #[derive(Archive, ArchiveWith)]
+    #[archive_with(rkyv = "0.8", from(remote::Remote))]
+    pub struct FallibleGetter {
+        #[archive_with(getter = "remote::Remote::parsed", fallible)]
+        pub parsed: u32,
+    }
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(remote::UserId), transparent)]
+    pub struct Transparent(pub u64);
+
+    #[derive(Archive, ArchiveWith, DeserializeWith)]
+    #[archive_with(rkyv = "0.8", from(remote::Address))]
+    pub struct ViaInto {
+        #[archive_with(from(Ipv4Addr), via_into(u32))]
+        pub ip: u32,
+    }
+}
+
+#[test]
+fn public_mirrors() {
+    use public::remote::{Address, Remote, UserId};
+
+    let remote = Remote::new("12");
+
+    let bytes = serialize::<public::FallibleGetter, _>(&remote);
+    let archived = archive::<public::FallibleGetter, Remote>(&bytes);
+    assert_eq!(archived.parsed, 12);
+
+    roundtrip::<public::Transparent, _>(&UserId::from(1));
+
+    let address = Address {
+        ip: Ipv4Addr::LOCALHOST,
+    };
+
+    roundtrip::<public::ViaInto, _>(&address);
+}
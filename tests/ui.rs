@@ -0,0 +1,8 @@
+// Without `rkyv = "..."`, the derives target rkyv 0.8 if its feature is enabled
+#![cfg(not(feature = "rkyv-0_8"))]
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
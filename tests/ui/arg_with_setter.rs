@@ -0,0 +1,27 @@
+use rkyv::Archive;
+use rkyv_with::DeserializeWith;
+
+struct Remote {
+    a: u8,
+    b: u8,
+}
+
+impl Remote {
+    fn new(a: u8) -> Self {
+        Self { a, b: 0 }
+    }
+
+    fn set_b(&mut self, b: u8) {
+        self.b = b;
+    }
+}
+
+#[derive(Archive, DeserializeWith)]
+#[archive_with(from(Remote), constructor = "Remote::new")]
+struct Example {
+    a: u8,
+    #[archive_with(setter = "Remote::set_b", arg = 0)]
+    b: u8,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use rkyv::Archive;
+use rkyv_with::DeserializeWith;
+
+struct Remote {
+    a: u8,
+    b: u8,
+}
+
+#[derive(Archive, DeserializeWith)]
+#[archive_with(from(Remote))]
+struct Example {
+    a: u8,
+    #[archive_with(arg = 0)]
+    b: u8,
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use std::borrow::Cow;
+
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+struct Remote {
+    name: String,
+}
+
+impl Remote {
+    fn name(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.name)
+    }
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote))]
+struct Example {
+    #[archive_with(getter = "Remote::name", borrow(str))]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+struct Remote {
+    a: u32,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote))]
+struct Example {
+    #[archive_with(from(u32), from(u64))]
+    a: u32,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote, Remote))]
+struct ExampleTop {
+    a: u32,
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+enum Remote {
+    A(u32),
+}
+
+fn get_a(_: &Remote) -> u32 {
+    0
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote))]
+enum Example {
+    A(#[archive_with(getter = "get_a")] u32),
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+struct Remote {
+    a: u8,
+    // Changed from `u8` after the mirror was written
+    b: u16,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote), exhaustive)]
+struct Example {
+    a: u8,
+    b: u8,
+}
+
+fn main() {}
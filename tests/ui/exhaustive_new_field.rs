@@ -0,0 +1,18 @@
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+struct Remote {
+    a: u8,
+    b: u8,
+    // Added to the original type after the mirror was written
+    c: u8,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote), exhaustive)]
+struct Example {
+    a: u8,
+    b: u8,
+}
+
+fn main() {}
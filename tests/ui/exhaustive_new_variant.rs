@@ -0,0 +1,18 @@
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+enum Remote {
+    A,
+    B(u8),
+    // Added to the original type after the mirror was written
+    C,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote), exhaustive)]
+enum Example {
+    A,
+    B(u8),
+}
+
+fn main() {}
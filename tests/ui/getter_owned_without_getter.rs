@@ -0,0 +1,15 @@
+use rkyv::Archive;
+use rkyv_with::ArchiveWith;
+
+struct Remote {
+    a: u32,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote))]
+struct Example {
+    #[archive_with(getter_owned)]
+    a: u32,
+}
+
+fn main() {}
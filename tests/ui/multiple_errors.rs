@@ -0,0 +1,21 @@
+use rkyv::Archive;
+use rkyv_with::{ArchiveWith, DeserializeWith};
+
+struct Remote {
+    a: u32,
+    b: u32,
+    c: u32,
+}
+
+#[derive(Archive, ArchiveWith, DeserializeWith)]
+#[archive_with(from(Remote), unknown)]
+struct Example {
+    #[archive_with(getter_owned)]
+    a: u32,
+    #[archive_with(from(u32), from(u32))]
+    b: u32,
+    #[archive_with(nonexistent)]
+    c: u32,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use rkyv::Archive;
+use rkyv_with::{ArchiveWith, DeserializeWith};
+
+struct Remote {
+    network: Network,
+}
+
+struct Network {
+    port: u16,
+}
+
+#[derive(Archive, ArchiveWith, DeserializeWith)]
+#[archive_with(from(Remote))]
+struct Example {
+    #[archive_with(path = "network.port")]
+    port: u16,
+}
+
+fn main() {}
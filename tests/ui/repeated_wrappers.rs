@@ -0,0 +1,18 @@
+use std::num::NonZeroU64;
+
+use rkyv::{with::Niche, Archive};
+use rkyv_with::ArchiveWith;
+
+struct Remote {
+    a: Option<NonZeroU64>,
+}
+
+#[derive(Archive, ArchiveWith)]
+#[archive_with(from(Remote))]
+struct Example {
+    #[with(Niche)]
+    #[archive_with(via(Niche))]
+    a: Option<NonZeroU64>,
+}
+
+fn main() {}